@@ -6,6 +6,7 @@
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
+use std::collections::HashSet;
 
 /// A complete query consisting of clauses.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -24,6 +25,7 @@ pub enum Clause {
     Limit(LimitClause),
     Skip(SkipClause),
     Create(CreateClause),
+    Merge(MergeClause),
     Set(SetClause),
     Delete(DeleteClause),
     With(WithClause),
@@ -88,6 +90,16 @@ pub struct CreateClause {
     pub pattern: Pattern,
 }
 
+/// MERGE clause for matching or creating a pattern.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MergeClause {
+    pub pattern: Pattern,
+    /// SET items applied when the pattern had to be created
+    pub on_create: Vec<SetItem>,
+    /// SET items applied when the pattern already existed
+    pub on_match: Vec<SetItem>,
+}
+
 /// SET clause for updating properties.
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct SetClause {
@@ -197,8 +209,13 @@ pub enum Expr {
     },
     /// Unary operation
     Unary { op: UnaryOp, expr: Box<Expr> },
-    /// Function call
-    FunctionCall { name: String, args: Vec<Expr> },
+    /// Function call, with `distinct` set for `f(DISTINCT ...)`
+    FunctionCall {
+        name: String,
+        args: Vec<Expr>,
+        #[serde(default)]
+        distinct: bool,
+    },
     /// CASE expression
     Case {
         operand: Option<Box<Expr>>,
@@ -226,6 +243,9 @@ pub enum Expr {
     Exists { pattern: Pattern },
     /// COUNT subquery
     Count { pattern: Pattern },
+    /// Membership in a literal list, prepared by the optimizer from
+    /// `expr IN [...]`
+    InSet { expr: Box<Expr>, set: LiteralSet },
 }
 
 /// Literal values.
@@ -239,6 +259,104 @@ pub enum Literal {
     String(String),
 }
 
+/// Deduplicated literals hashed for membership tests.
+///
+/// Integral floats share a key with the equal integer, as they compare
+/// equal. Serialized as the list of literals it was built from.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<Literal>", into = "Vec<Literal>")]
+pub struct LiteralSet {
+    /// First occurrence of each value, in list order
+    literals: Vec<Literal>,
+    keys: HashSet<LiteralKey>,
+    has_null: bool,
+}
+
+impl LiteralSet {
+    /// Whether `needle` is in the set.
+    ///
+    /// Follows Cypher null semantics: a null needle, or a miss against a set
+    /// containing null, yields `None`.
+    #[must_use]
+    pub fn contains(&self, needle: &Literal) -> Option<bool> {
+        if matches!(needle, Literal::Null) {
+            return None;
+        }
+        if LiteralKey::of(needle).is_some_and(|key| self.keys.contains(&key)) {
+            Some(true)
+        } else if self.has_null {
+            None
+        } else {
+            Some(false)
+        }
+    }
+
+    /// The distinct literals, in their original order.
+    #[must_use]
+    pub fn literals(&self) -> &[Literal] {
+        &self.literals
+    }
+}
+
+impl From<Vec<Literal>> for LiteralSet {
+    fn from(items: Vec<Literal>) -> Self {
+        let mut set = Self {
+            literals: Vec::with_capacity(items.len()),
+            keys: HashSet::with_capacity(items.len()),
+            has_null: false,
+        };
+        for literal in items {
+            let new = match LiteralKey::of(&literal) {
+                Some(key) => set.keys.insert(key),
+                None if matches!(literal, Literal::Null) => {
+                    !std::mem::replace(&mut set.has_null, true)
+                }
+                // NaN equals nothing, so every occurrence is distinct
+                None => true,
+            };
+            if new {
+                set.literals.push(literal);
+            }
+        }
+        set
+    }
+}
+
+impl From<LiteralSet> for Vec<Literal> {
+    fn from(set: LiteralSet) -> Self {
+        set.literals
+    }
+}
+
+/// Hashable form of a non-null literal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+enum LiteralKey {
+    Boolean(bool),
+    Integer(i64),
+    Float(u64),
+    String(String),
+}
+
+impl LiteralKey {
+    /// Key for `literal`; null and NaN have none.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    fn of(literal: &Literal) -> Option<Self> {
+        match literal {
+            Literal::Null => None,
+            Literal::Boolean(b) => Some(Self::Boolean(*b)),
+            Literal::Integer(n) => Some(Self::Integer(*n)),
+            Literal::Float(f) if f.is_nan() => None,
+            Literal::Float(f)
+                if f.fract() == 0.0 && (i64::MIN as f64..i64::MAX as f64).contains(f) =>
+            {
+                Some(Self::Integer(*f as i64))
+            }
+            Literal::Float(f) => Some(Self::Float(f.to_bits())),
+            Literal::String(s) => Some(Self::String(s.clone())),
+        }
+    }
+}
+
 /// Binary operators.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum BinaryOp {
@@ -0,0 +1,203 @@
+//! LRU cache of compiled execution plans.
+//!
+//! Keys are built from a query's tokens, so whitespace and comments do not
+//! split entries. Query parameters (`$name`) stay symbolic in the plan, so
+//! one cached plan serves every set of parameter values.
+
+use crate::parser::{Lexer, LexerOptions, Token};
+use crate::planner::ExecutionPlan;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+/// Default number of plans kept by a `QueryEngine`.
+pub const DEFAULT_CAPACITY: usize = 128;
+
+/// Cache hit/miss counters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    /// Lookups answered from the cache
+    pub hits: u64,
+    /// Lookups that required parsing and planning
+    pub misses: u64,
+}
+
+/// Least-recently-used plan cache.
+#[derive(Debug)]
+pub struct PlanCache {
+    capacity: usize,
+    /// Plan and the tick of its last use
+    entries: HashMap<String, (ExecutionPlan, u64)>,
+    tick: u64,
+    stats: CacheStats,
+}
+
+impl PlanCache {
+    /// Create a cache holding up to `capacity` plans; 0 disables caching.
+    #[must_use]
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: HashMap::with_capacity(capacity),
+            tick: 0,
+            stats: CacheStats::default(),
+        }
+    }
+
+    /// Look up the plan for `key`, marking it most recently used.
+    pub fn get(&mut self, key: &str) -> Option<ExecutionPlan> {
+        self.tick += 1;
+        if let Some((plan, last_used)) = self.entries.get_mut(key) {
+            *last_used = self.tick;
+            self.stats.hits += 1;
+            Some(plan.clone())
+        } else {
+            self.stats.misses += 1;
+            None
+        }
+    }
+
+    /// Store a plan, evicting the least recently used one when full.
+    pub fn insert(&mut self, key: String, plan: ExecutionPlan) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if self.entries.len() >= self.capacity && !self.entries.contains_key(&key) {
+            // Linear scan: capacities are small and eviction is off the hot path
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                self.entries.remove(&oldest);
+            }
+        }
+
+        self.tick += 1;
+        self.entries.insert(key, (plan, self.tick));
+    }
+
+    /// Number of cached plans.
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Check if the cache holds no plans.
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Hit/miss counters since creation.
+    #[must_use]
+    pub const fn stats(&self) -> CacheStats {
+        self.stats
+    }
+
+    /// Drop all cached plans.
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+impl Default for PlanCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+/// Cache key for `query`: the source text of each token it lexes to under
+/// `options`, so formatting and comments share a cache entry.
+///
+/// Returns `None` if the query does not lex; such queries are not cached.
+#[must_use]
+pub fn normalize_query(query: &str, options: LexerOptions) -> Option<String> {
+    let mut lexer = Lexer::with_options(query, options);
+    // Comment settings change how the same text lexes
+    let mut key = format!("{options:?}");
+
+    loop {
+        if lexer.next_token().ok()? == Token::Eof {
+            return Some(key);
+        }
+        // Debug quoting keeps token boundaries unambiguous
+        let _ = write!(key, " {:?}", lexer.token_text());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::planner::PlanNode;
+
+    fn plan(rows: usize) -> ExecutionPlan {
+        ExecutionPlan {
+            root: PlanNode::EmptyResult,
+            estimated_cost: 0.0,
+            estimated_rows: rows,
+            required_indexes: vec![],
+        }
+    }
+
+    #[test]
+    fn test_evicts_least_recently_used() {
+        let mut cache = PlanCache::new(2);
+        cache.insert("a".to_string(), plan(1));
+        cache.insert("b".to_string(), plan(2));
+
+        // Touch "a" so "b" becomes the eviction candidate
+        assert!(cache.get("a").is_some());
+        cache.insert("c".to_string(), plan(3));
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("b").is_none());
+        assert_eq!(cache.get("a").map(|p| p.estimated_rows), Some(1));
+        assert_eq!(cache.get("c").map(|p| p.estimated_rows), Some(3));
+    }
+
+    #[test]
+    fn test_zero_capacity_disables_caching() {
+        let mut cache = PlanCache::new(0);
+        cache.insert("a".to_string(), plan(1));
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn test_normalize_query() {
+        let key = |query| normalize_query(query, LexerOptions::default()).unwrap();
+
+        assert_eq!(
+            key("  MATCH (n)\n\tWHERE n.name = 'a  b'   RETURN n "),
+            key("MATCH (n) WHERE n.name = 'a  b' RETURN n /* all */")
+        );
+        assert_ne!(key("RETURN 'a  b'"), key("RETURN 'a b'"));
+        assert_ne!(key("RETURN n.on"), key("RETURN n.ON"));
+        assert!(normalize_query("RETURN 'open", LexerOptions::default()).is_none());
+    }
+
+    #[test]
+    fn test_normalize_query_keeps_line_comment_end() {
+        let key = |query| normalize_query(query, LexerOptions::default()).unwrap();
+
+        assert_ne!(
+            key("MATCH (n) RETURN n // note\n, n.name"),
+            key("MATCH (n) RETURN n // note , n.name")
+        );
+    }
+
+    #[test]
+    fn test_normalize_query_includes_options() {
+        let query = "RETURN 4 // 2";
+        let no_comments = LexerOptions {
+            line_comment: None,
+            ..LexerOptions::default()
+        };
+
+        assert_ne!(
+            normalize_query(query, LexerOptions::default()),
+            normalize_query(query, no_comments)
+        );
+    }
+}
@@ -24,14 +24,18 @@
 #![allow(clippy::module_name_repetitions)]
 
 pub mod ast;
+pub mod cache;
 pub mod executor;
 pub mod optimizer;
 pub mod parser;
 pub mod planner;
+mod printer;
+pub mod typecheck;
 
 use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
 use smallvec::SmallVec;
+use std::sync::{Mutex, MutexGuard, PoisonError};
 use thiserror::Error;
 
 #[cfg(feature = "wasm")]
@@ -39,10 +43,12 @@ use wasm_bindgen::prelude::*;
 
 // Re-exports for public API
 pub use ast::{BinaryOp, Expr, Literal, Query, UnaryOp};
+pub use cache::{CacheStats, PlanCache};
 pub use executor::{ExecutionContext, InMemoryGraph, QueryConfig, QueryExecutor, Row, Value};
-pub use optimizer::QueryOptimizer;
-pub use parser::QueryParser;
+pub use optimizer::{ArithmeticOverflow, QueryOptimizer};
+pub use parser::{LexerOptions, QueryParser};
 pub use planner::{ExecutionPlan, PlanNode, QueryPlanner};
+pub use typecheck::{ExprType, Schema, TypeChecker};
 
 /// Errors that can occur during query processing.
 #[derive(Debug, Error)]
@@ -80,6 +86,8 @@ pub struct QueryEngine {
     parser: QueryParser,
     planner: QueryPlanner,
     optimizer: QueryOptimizer,
+    plans: Mutex<PlanCache>,
+    schema: Option<Schema>,
 }
 
 impl QueryEngine {
@@ -89,6 +97,54 @@ impl QueryEngine {
         Self::default()
     }
 
+    /// Create a query engine caching up to `capacity` compiled plans.
+    ///
+    /// A capacity of 0 disables the plan cache.
+    #[must_use]
+    pub fn with_plan_cache_capacity(capacity: usize) -> Self {
+        Self {
+            plans: Mutex::new(PlanCache::new(capacity)),
+            ..Self::default()
+        }
+    }
+
+    /// Type-check queries against `schema` when planning.
+    ///
+    /// Clears the plan cache, since cached plans were checked without it.
+    #[must_use]
+    pub fn with_schema(mut self, schema: Schema) -> Self {
+        self.schema = Some(schema);
+        self.clear_plan_cache();
+        self
+    }
+
+    /// Set how constant folding treats integer overflow.
+    ///
+    /// Clears the plan cache, since cached plans were folded under the
+    /// previous policy.
+    #[must_use]
+    pub fn with_overflow_policy(mut self, policy: ArithmeticOverflow) -> Self {
+        self.optimizer = std::mem::take(&mut self.optimizer).with_arithmetic_overflow(policy);
+        self.clear_plan_cache();
+        self
+    }
+
+    /// Plan cache hit/miss counters.
+    #[must_use]
+    pub fn plan_cache_stats(&self) -> CacheStats {
+        self.plan_cache().stats()
+    }
+
+    /// Drop all cached plans.
+    pub fn clear_plan_cache(&self) {
+        self.plan_cache().clear();
+    }
+
+    fn plan_cache(&self) -> MutexGuard<'_, PlanCache> {
+        // A panic mid-update cannot leave the cache inconsistent
+        self.plans.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
     /// Parse a query string into an AST.
     ///
     /// # Errors
@@ -102,20 +158,35 @@ impl QueryEngine {
     ///
     /// # Errors
     ///
-    /// Returns an error if planning or optimization fails.
+    /// Returns `QueryError::TypeError` if an expression is ill-typed, or an
+    /// error if planning or optimization fails.
     pub fn plan(&self, query: &Query) -> Result<ExecutionPlan> {
+        TypeChecker::new(self.schema.as_ref()).check(query)?;
         let plan = self.planner.plan(query)?;
         self.optimizer.optimize(plan)
     }
 
     /// Parse, plan, and optimize a query string.
     ///
+    /// Plans are cached by their tokens, so repeated queries skip parsing and
+    /// planning. Parameters stay symbolic, so a cached plan is reused across
+    /// parameter values.
+    ///
     /// # Errors
     ///
     /// Returns an error if any stage fails.
     pub fn compile(&self, query: &str) -> Result<ExecutionPlan> {
+        let key = cache::normalize_query(query, self.parser.options());
+        if let Some(plan) = key.as_deref().and_then(|key| self.plan_cache().get(key)) {
+            return Ok(plan);
+        }
+
         let ast = self.parse(query)?;
-        self.plan(&ast)
+        let plan = self.plan(&ast)?;
+        if let Some(key) = key {
+            self.plan_cache().insert(key, plan.clone());
+        }
+        Ok(plan)
     }
 }
 
@@ -204,4 +275,64 @@ mod tests {
         let result = engine.compile("MATCH (n:Person) RETURN n");
         assert!(result.is_ok());
     }
+
+    #[test]
+    fn test_compile_uses_plan_cache() {
+        let engine = QueryEngine::new();
+
+        engine.compile("MATCH (n:Person) WHERE n.age > $min RETURN n").unwrap();
+        engine.compile("MATCH (n:Person)\n  WHERE n.age > $min\n  RETURN n").unwrap();
+
+        // Only the first compile reached the planner
+        assert_eq!(engine.plan_cache_stats(), CacheStats { hits: 1, misses: 1 });
+
+        engine.compile("MATCH (n:Person) RETURN n").unwrap();
+        assert_eq!(engine.plan_cache_stats().misses, 2);
+    }
+
+    #[test]
+    fn test_plan_cache_separates_line_comment_end() {
+        let engine = QueryEngine::new();
+
+        let first = engine.compile("MATCH (n) RETURN n // note\n, n.name").unwrap();
+        let second = engine.compile("MATCH (n) RETURN n // note , n.name").unwrap();
+
+        assert_eq!(engine.plan_cache_stats(), CacheStats { hits: 0, misses: 2 });
+        assert_ne!(format!("{:?}", first.root), format!("{:?}", second.root));
+    }
+
+    #[test]
+    fn test_plan_cache_disabled() {
+        let engine = QueryEngine::with_plan_cache_capacity(0);
+
+        engine.compile("MATCH (n) RETURN n").unwrap();
+        engine.compile("MATCH (n) RETURN n").unwrap();
+
+        assert_eq!(engine.plan_cache_stats().hits, 0);
+    }
+
+    #[test]
+    fn test_compile_reports_type_errors_with_schema() {
+        let query = "MATCH (n:Person) WHERE n.name + 5 > 0 RETURN n";
+        assert!(QueryEngine::new().compile(query).is_ok());
+
+        let engine = QueryEngine::new()
+            .with_schema(Schema::new().with_property("Person", "name", ExprType::String));
+        assert!(matches!(engine.compile(query), Err(QueryError::TypeError { .. })));
+    }
+
+    #[test]
+    fn test_overflow_policy() {
+        let query = "RETURN 9223372036854775807 + 1 AS n";
+        assert!(matches!(
+            QueryEngine::new().compile(query),
+            Err(QueryError::OptimizationError(_))
+        ));
+
+        let engine = QueryEngine::new().with_overflow_policy(ArithmeticOverflow::Wrap);
+        let PlanNode::Project { items, .. } = engine.compile(query).unwrap().root else {
+            panic!("expected Project at the root");
+        };
+        assert_eq!(items[0].0, Expr::Literal(Literal::Integer(i64::MIN)));
+    }
 }
@@ -10,11 +10,41 @@ use crate::ast::*;
 use crate::planner::{ExecutionPlan, PlanNode};
 use crate::{QueryError, Result};
 
+/// How constant folding handles integer arithmetic that overflows `i64`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArithmeticOverflow {
+    /// Wrap around in two's complement
+    Wrap,
+    /// Clamp to `i64::MIN` / `i64::MAX`
+    Saturate,
+    /// Fail optimization with `QueryError::OptimizationError`
+    #[default]
+    Error,
+}
+
+/// Integer operators folded under the overflow policy.
+#[derive(Debug, Clone, Copy)]
+enum IntOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Mod,
+}
+
 /// Query optimizer that transforms execution plans.
-#[derive(Debug, Default)]
+#[derive(Debug)]
 pub struct QueryOptimizer {
     /// Maximum optimization iterations
     max_iterations: usize,
+    /// Overflow policy for folded integer arithmetic
+    arithmetic_overflow: ArithmeticOverflow,
+}
+
+impl Default for QueryOptimizer {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl QueryOptimizer {
@@ -22,9 +52,17 @@ impl QueryOptimizer {
     pub fn new() -> Self {
         Self {
             max_iterations: 10,
+            arithmetic_overflow: ArithmeticOverflow::default(),
         }
     }
 
+    /// Set how folding treats integer overflow.
+    #[must_use]
+    pub fn with_arithmetic_overflow(mut self, policy: ArithmeticOverflow) -> Self {
+        self.arithmetic_overflow = policy;
+        self
+    }
+
     /// Optimize an execution plan.
     pub fn optimize(&self, mut plan: ExecutionPlan) -> Result<ExecutionPlan> {
         for _ in 0..self.max_iterations {
@@ -55,7 +93,7 @@ impl QueryOptimizer {
     fn fold_constants(&self, node: PlanNode) -> Result<PlanNode> {
         match node {
             PlanNode::Filter { input, predicate } => {
-                let folded_predicate = self.fold_expr(predicate);
+                let folded_predicate = self.fold_expr(predicate)?;
 
                 // If predicate is always true, eliminate filter
                 if let Expr::Literal(Literal::Boolean(true)) = &folded_predicate {
@@ -76,15 +114,15 @@ impl QueryOptimizer {
                 input: Box::new(self.fold_constants(*input)?),
                 items: items
                     .into_iter()
-                    .map(|(e, n)| (self.fold_expr(e), n))
-                    .collect(),
+                    .map(|(e, n)| Ok((self.fold_expr(e)?, n)))
+                    .collect::<Result<_>>()?,
             }),
             PlanNode::Sort { input, items } => Ok(PlanNode::Sort {
                 input: Box::new(self.fold_constants(*input)?),
                 items: items
                     .into_iter()
-                    .map(|(e, asc)| (self.fold_expr(e), asc))
-                    .collect(),
+                    .map(|(e, asc)| Ok((self.fold_expr(e)?, asc)))
+                    .collect::<Result<_>>()?,
             }),
             PlanNode::Limit { input, count } => Ok(PlanNode::Limit {
                 input: Box::new(self.fold_constants(*input)?),
@@ -125,22 +163,27 @@ impl QueryOptimizer {
             } => Ok(PlanNode::NestedLoopJoin {
                 outer: Box::new(self.fold_constants(*outer)?),
                 inner: Box::new(self.fold_constants(*inner)?),
-                condition: condition.map(|c| self.fold_expr(c)),
+                condition: condition.map(|c| self.fold_expr(c)).transpose()?,
             }),
             other => Ok(other),
         }
     }
 
-    fn fold_expr(&self, expr: Expr) -> Expr {
-        match expr {
+    fn fold_expr(&self, expr: Expr) -> Result<Expr> {
+        let folded = match expr {
             Expr::Binary { left, op, right } => {
-                let left = self.fold_expr(*left);
-                let right = self.fold_expr(*right);
+                let left = self.fold_expr(*left)?;
+                let right = self.fold_expr(*right)?;
+
+                // Membership in a literal list
+                if op == BinaryOp::In && let Expr::List(items) = right {
+                    return Ok(Self::fold_in(left, items));
+                }
 
                 // Try to evaluate constant expressions
                 if let (Expr::Literal(l), Expr::Literal(r)) = (&left, &right) {
-                    if let Some(result) = self.eval_binary(l, op, r) {
-                        return Expr::Literal(result);
+                    if let Some(result) = self.eval_binary(l, op, r)? {
+                        return Ok(Expr::Literal(result));
                     }
                 }
 
@@ -183,14 +226,14 @@ impl QueryOptimizer {
                 }
             }
             Expr::Unary { op, expr } => {
-                let expr = self.fold_expr(*expr);
+                let expr = self.fold_expr(*expr)?;
 
                 match (&op, &expr) {
                     (UnaryOp::Not, Expr::Literal(Literal::Boolean(b))) => {
                         Expr::Literal(Literal::Boolean(!b))
                     }
                     (UnaryOp::Neg, Expr::Literal(Literal::Integer(n))) => {
-                        Expr::Literal(Literal::Integer(-n))
+                        Expr::Literal(Literal::Integer(self.int_arith(IntOp::Sub, 0, *n)?))
                     }
                     (UnaryOp::Neg, Expr::Literal(Literal::Float(n))) => {
                         Expr::Literal(Literal::Float(-n))
@@ -204,40 +247,68 @@ impl QueryOptimizer {
                     },
                 }
             }
-            Expr::FunctionCall { name, args } => Expr::FunctionCall {
+            Expr::FunctionCall {
                 name,
-                args: args.into_iter().map(|a| self.fold_expr(a)).collect(),
+                args,
+                distinct,
+            } => Expr::FunctionCall {
+                name,
+                args: args
+                    .into_iter()
+                    .map(|a| self.fold_expr(a))
+                    .collect::<Result<_>>()?,
+                distinct,
             },
-            Expr::List(items) => Expr::List(items.into_iter().map(|i| self.fold_expr(i)).collect()),
+            Expr::List(items) => Expr::List(
+                items
+                    .into_iter()
+                    .map(|i| self.fold_expr(i))
+                    .collect::<Result<_>>()?,
+            ),
             Expr::Property { expr, name } => Expr::Property {
-                expr: Box::new(self.fold_expr(*expr)),
+                expr: Box::new(self.fold_expr(*expr)?),
                 name,
             },
             Expr::Index { expr, index } => Expr::Index {
-                expr: Box::new(self.fold_expr(*expr)),
-                index: Box::new(self.fold_expr(*index)),
+                expr: Box::new(self.fold_expr(*expr)?),
+                index: Box::new(self.fold_expr(*index)?),
+            },
+            Expr::InSet { expr, set } => match self.fold_expr(*expr)? {
+                Expr::Literal(needle) => {
+                    Expr::Literal(set.contains(&needle).map_or(Literal::Null, Literal::Boolean))
+                }
+                expr => Expr::InSet {
+                    expr: Box::new(expr),
+                    set,
+                },
             },
             other => other,
-        }
+        };
+        Ok(folded)
     }
 
-    fn eval_binary(&self, left: &Literal, op: BinaryOp, right: &Literal) -> Option<Literal> {
-        match (left, op, right) {
+    fn eval_binary(
+        &self,
+        left: &Literal,
+        op: BinaryOp,
+        right: &Literal,
+    ) -> Result<Option<Literal>> {
+        let result = match (left, op, right) {
             // Integer arithmetic
             (Literal::Integer(a), BinaryOp::Add, Literal::Integer(b)) => {
-                Some(Literal::Integer(a + b))
+                Some(Literal::Integer(self.int_arith(IntOp::Add, *a, *b)?))
             }
             (Literal::Integer(a), BinaryOp::Sub, Literal::Integer(b)) => {
-                Some(Literal::Integer(a - b))
+                Some(Literal::Integer(self.int_arith(IntOp::Sub, *a, *b)?))
             }
             (Literal::Integer(a), BinaryOp::Mul, Literal::Integer(b)) => {
-                Some(Literal::Integer(a * b))
+                Some(Literal::Integer(self.int_arith(IntOp::Mul, *a, *b)?))
             }
             (Literal::Integer(a), BinaryOp::Div, Literal::Integer(b)) if *b != 0 => {
-                Some(Literal::Integer(a / b))
+                Some(Literal::Integer(self.int_arith(IntOp::Div, *a, *b)?))
             }
             (Literal::Integer(a), BinaryOp::Mod, Literal::Integer(b)) if *b != 0 => {
-                Some(Literal::Integer(a % b))
+                Some(Literal::Integer(self.int_arith(IntOp::Mod, *a, *b)?))
             }
 
             // Float arithmetic
@@ -297,9 +368,114 @@ impl QueryOptimizer {
             }
 
             _ => None,
+        };
+        Ok(result)
+    }
+
+    /// Apply integer `op`, resolving overflow by the configured policy.
+    fn int_arith(&self, op: IntOp, a: i64, b: i64) -> Result<i64> {
+        let (checked, wrapped, saturated) = match op {
+            IntOp::Add => (a.checked_add(b), a.wrapping_add(b), a.saturating_add(b)),
+            IntOp::Sub => (a.checked_sub(b), a.wrapping_sub(b), a.saturating_sub(b)),
+            IntOp::Mul => (a.checked_mul(b), a.wrapping_mul(b), a.saturating_mul(b)),
+            IntOp::Div => (a.checked_div(b), a.wrapping_div(b), a.saturating_div(b)),
+            // i64::MIN % -1 is mathematically 0, which wrapping already gives
+            IntOp::Mod => (a.checked_rem(b), a.wrapping_rem(b), a.wrapping_rem(b)),
+        };
+
+        match (checked, self.arithmetic_overflow) {
+            (Some(value), _) => Ok(value),
+            (None, ArithmeticOverflow::Wrap) => Ok(wrapped),
+            (None, ArithmeticOverflow::Saturate) => Ok(saturated),
+            (None, ArithmeticOverflow::Error) => Err(QueryError::OptimizationError(format!(
+                "Integer overflow folding {a} {op:?} {b}"
+            ))),
+        }
+    }
+
+    /// Fold `left IN [items]` to a literal when both sides are constant.
+    ///
+    /// Otherwise a list of literals is prepared as a hash set for runtime
+    /// membership tests, and any other list loses its repeated literals.
+    fn fold_in(left: Expr, items: Vec<Expr>) -> Expr {
+        if let Expr::Literal(needle) = &left
+            && let Some(result) = Self::eval_in(needle, &items)
+        {
+            return Expr::Literal(result);
+        }
+
+        let literals: Option<Vec<Literal>> = items
+            .iter()
+            .map(|item| match item {
+                Expr::Literal(l) => Some(l.clone()),
+                _ => None,
+            })
+            .collect();
+        match literals {
+            Some(literals) => Expr::InSet {
+                expr: Box::new(left),
+                set: LiteralSet::from(literals),
+            },
+            None => Expr::Binary {
+                left: Box::new(left),
+                op: BinaryOp::In,
+                right: Box::new(Expr::List(Self::dedup_literals(items))),
+            },
+        }
+    }
+
+    /// Evaluate `needle IN items` when every item is a literal.
+    ///
+    /// Follows Cypher null semantics: a null needle, or a miss against a list
+    /// containing null, yields null.
+    fn eval_in(needle: &Literal, items: &[Expr]) -> Option<Literal> {
+        let literals: Vec<&Literal> = items
+            .iter()
+            .map(|item| match item {
+                Expr::Literal(l) => Some(l),
+                _ => None,
+            })
+            .collect::<Option<_>>()?;
+
+        if matches!(needle, Literal::Null) {
+            return Some(Literal::Null);
+        }
+
+        if literals.iter().any(|l| Self::literal_eq(needle, l)) {
+            Some(Literal::Boolean(true))
+        } else if literals.iter().any(|l| matches!(l, Literal::Null)) {
+            Some(Literal::Null)
+        } else {
+            Some(Literal::Boolean(false))
+        }
+    }
+
+    /// Literal equality with integer/float coercion.
+    #[allow(clippy::cast_precision_loss)]
+    fn literal_eq(a: &Literal, b: &Literal) -> bool {
+        match (a, b) {
+            (Literal::Integer(x), Literal::Float(y)) | (Literal::Float(y), Literal::Integer(x)) => {
+                (*x as f64 - y).abs() < f64::EPSILON
+            }
+            _ => a == b,
         }
     }
 
+    /// Drop repeated literal entries from an IN list, keeping first occurrences.
+    ///
+    /// Non-literal items are kept as-is since their values are unknown until
+    /// execution.
+    fn dedup_literals(items: Vec<Expr>) -> Vec<Expr> {
+        let mut result: Vec<Expr> = Vec::with_capacity(items.len());
+        for item in items {
+            let duplicate = matches!(item, Expr::Literal(_)) && result.contains(&item);
+            if !duplicate {
+                result.push(item);
+            }
+        }
+        result
+    }
+
     /// Push predicates down closer to data sources.
     fn push_down_predicates(&self, node: PlanNode) -> Result<PlanNode> {
         match node {
@@ -327,6 +503,20 @@ impl QueryOptimizer {
                             })
                         }
                     }
+                    // Never push below an aggregate: the predicate may read
+                    // aggregated values (HAVING semantics)
+                    PlanNode::Aggregate {
+                        input,
+                        group_by,
+                        aggregates,
+                    } => Ok(PlanNode::Filter {
+                        input: Box::new(PlanNode::Aggregate {
+                            input: Box::new(self.push_down_predicates(*input)?),
+                            group_by,
+                            aggregates,
+                        }),
+                        predicate,
+                    }),
                     // Push filter below sort
                     PlanNode::Sort { input, items } => {
                         Ok(PlanNode::Sort {
@@ -422,6 +612,15 @@ impl QueryOptimizer {
                 input: Box::new(self.push_down_predicates(*input)?),
                 count,
             }),
+            PlanNode::Aggregate {
+                input,
+                group_by,
+                aggregates,
+            } => Ok(PlanNode::Aggregate {
+                input: Box::new(self.push_down_predicates(*input)?),
+                group_by,
+                aggregates,
+            }),
             PlanNode::Expand {
                 input,
                 from_variable,
@@ -445,9 +644,34 @@ impl QueryOptimizer {
         }
     }
 
-    fn can_push_through_project(&self, predicate: &Expr, _items: &[(Expr, String)]) -> bool {
-        // Simple check: if predicate only uses variables, it can be pushed
-        self.expr_uses_only_variables(predicate)
+    fn can_push_through_project(&self, predicate: &Expr, items: &[(Expr, String)]) -> bool {
+        if !self.expr_uses_only_variables(predicate) {
+            return false;
+        }
+
+        // Every referenced variable must pass through the projection unchanged;
+        // aliases computed by the projection don't exist below it
+        let mut variables = Vec::new();
+        Self::collect_variables(predicate, &mut variables);
+        variables.iter().all(|var| {
+            items.iter().any(|(expr, alias)| {
+                alias == var && matches!(expr, Expr::Variable(name) if name == var)
+            })
+        })
+    }
+
+    fn collect_variables<'e>(expr: &'e Expr, out: &mut Vec<&'e str>) {
+        match expr {
+            Expr::Variable(name) => out.push(name),
+            Expr::Property { expr, .. } | Expr::Unary { expr, .. } => {
+                Self::collect_variables(expr, out);
+            }
+            Expr::Binary { left, right, .. } => {
+                Self::collect_variables(left, out);
+                Self::collect_variables(right, out);
+            }
+            _ => {}
+        }
     }
 
     fn expr_uses_only_variables(&self, expr: &Expr) -> bool {
@@ -525,13 +749,15 @@ impl QueryOptimizer {
         match node {
             // Skip 0 is identity
             PlanNode::Skip { input, count: 0 } => self.eliminate_redundant(*input),
-            // Distinct after distinct is redundant
+            // Distinct after distinct, or over already-unique rows, is redundant
             PlanNode::Distinct {
                 input,
                 columns,
             } => {
                 let inner = self.eliminate_redundant(*input)?;
-                if matches!(inner, PlanNode::Distinct { .. }) {
+                if matches!(inner, PlanNode::Distinct { .. })
+                    || Self::rows_unique(&inner, &columns)
+                {
                     Ok(inner)
                 } else {
                     Ok(PlanNode::Distinct {
@@ -567,6 +793,40 @@ impl QueryOptimizer {
         }
     }
 
+    /// Whether `node` provably never emits two rows that agree on `columns`.
+    fn rows_unique(node: &PlanNode, columns: &[String]) -> bool {
+        if Self::at_most_one_row(node) {
+            return true;
+        }
+
+        match node {
+            // One row per group, so projecting every grouping key keeps rows distinct
+            PlanNode::Project { input, items } => match input.as_ref() {
+                PlanNode::Aggregate { group_by, .. } => group_by.iter().all(|key| {
+                    items
+                        .iter()
+                        .any(|(expr, alias)| expr == key && columns.contains(alias))
+                }),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+
+    /// Whether `node` produces at most one row.
+    fn at_most_one_row(node: &PlanNode) -> bool {
+        match node {
+            PlanNode::EmptyResult => true,
+            PlanNode::Aggregate { group_by, .. } => group_by.is_empty(),
+            PlanNode::Limit { input, count } => *count <= 1 || Self::at_most_one_row(input),
+            PlanNode::Filter { input, .. }
+            | PlanNode::Project { input, .. }
+            | PlanNode::Sort { input, .. }
+            | PlanNode::Skip { input, .. } => Self::at_most_one_row(input),
+            _ => false,
+        }
+    }
+
     /// Reorder joins for better performance.
     fn reorder_joins(&self, node: PlanNode) -> Result<PlanNode> {
         // Basic join reordering: prefer smaller tables on build side
@@ -750,10 +1010,38 @@ mod tests {
             right: Box::new(Expr::Literal(Literal::Integer(3))),
         };
 
-        let folded = optimizer.fold_expr(expr);
+        let folded = optimizer.fold_expr(expr).unwrap();
         assert_eq!(folded, Expr::Literal(Literal::Integer(5)));
     }
 
+    #[test]
+    fn test_integer_overflow_policies() {
+        let max_plus_one = || Expr::Binary {
+            left: Box::new(Expr::Literal(Literal::Integer(i64::MAX))),
+            op: BinaryOp::Add,
+            right: Box::new(Expr::Literal(Literal::Integer(1))),
+        };
+
+        let wrap = QueryOptimizer::new().with_arithmetic_overflow(ArithmeticOverflow::Wrap);
+        assert_eq!(
+            wrap.fold_expr(max_plus_one()).unwrap(),
+            Expr::Literal(Literal::Integer(i64::MIN))
+        );
+
+        let saturate =
+            QueryOptimizer::new().with_arithmetic_overflow(ArithmeticOverflow::Saturate);
+        assert_eq!(
+            saturate.fold_expr(max_plus_one()).unwrap(),
+            Expr::Literal(Literal::Integer(i64::MAX))
+        );
+
+        let error = QueryOptimizer::new().with_arithmetic_overflow(ArithmeticOverflow::Error);
+        assert!(matches!(
+            error.fold_expr(max_plus_one()),
+            Err(QueryError::OptimizationError(_))
+        ));
+    }
+
     #[test]
     fn test_boolean_simplification() {
         let optimizer = QueryOptimizer::new();
@@ -764,10 +1052,88 @@ mod tests {
             right: Box::new(Expr::Literal(Literal::Boolean(true))),
         };
 
-        let folded = optimizer.fold_expr(expr);
+        let folded = optimizer.fold_expr(expr).unwrap();
         assert_eq!(folded, Expr::Variable("x".to_string()));
     }
 
+    fn in_list(needle: Expr, items: &[i64]) -> Expr {
+        Expr::Binary {
+            left: Box::new(needle),
+            op: BinaryOp::In,
+            right: Box::new(Expr::List(
+                items
+                    .iter()
+                    .map(|&i| Expr::Literal(Literal::Integer(i)))
+                    .collect(),
+            )),
+        }
+    }
+
+    #[test]
+    fn test_in_constant_list_folding() {
+        let optimizer = QueryOptimizer::new();
+
+        let hit = in_list(Expr::Literal(Literal::Integer(3)), &[1, 2, 3]);
+        assert_eq!(
+            optimizer.fold_expr(hit).unwrap(),
+            Expr::Literal(Literal::Boolean(true))
+        );
+
+        let miss = in_list(Expr::Literal(Literal::Integer(5)), &[1, 2, 3]);
+        assert_eq!(
+            optimizer.fold_expr(miss).unwrap(),
+            Expr::Literal(Literal::Boolean(false))
+        );
+    }
+
+    #[test]
+    fn test_in_list_normalization() {
+        let optimizer = QueryOptimizer::new();
+
+        let property = Expr::Property {
+            expr: Box::new(Expr::Variable("n".to_string())),
+            name: "id".to_string(),
+        };
+        let folded = optimizer.fold_expr(in_list(property.clone(), &[1, 2, 2, 1, 3])).unwrap();
+
+        let Expr::InSet { expr, set } = folded else {
+            panic!("expected InSet, got {folded:?}");
+        };
+        assert_eq!(*expr, property);
+        let ints = [1, 2, 3].map(Literal::Integer);
+        assert_eq!(set.literals(), ints);
+        assert_eq!(set.contains(&Literal::Float(2.0)), Some(true));
+        assert_eq!(set.contains(&Literal::Integer(4)), Some(false));
+
+        // Unknown items keep the list; literal duplicates still go
+        let mut mixed = in_list(property.clone(), &[1, 1]);
+        if let Expr::Binary { right, .. } = &mut mixed
+            && let Expr::List(items) = right.as_mut()
+        {
+            items.push(Expr::Parameter("id".to_string()));
+        }
+        let Expr::Binary { right, .. } = optimizer.fold_expr(mixed).unwrap() else {
+            panic!("expected IN over a list");
+        };
+        assert_eq!(
+            *right,
+            Expr::List(vec![
+                Expr::Literal(Literal::Integer(1)),
+                Expr::Parameter("id".to_string())
+            ])
+        );
+    }
+
+    #[test]
+    fn test_in_set_null_semantics() {
+        let set = LiteralSet::from(vec![Literal::Integer(1), Literal::Null, Literal::Null]);
+
+        assert_eq!(set.literals(), [Literal::Integer(1), Literal::Null]);
+        assert_eq!(set.contains(&Literal::Integer(1)), Some(true));
+        assert_eq!(set.contains(&Literal::Integer(5)), None);
+        assert_eq!(set.contains(&Literal::Null), None);
+    }
+
     #[test]
     fn test_filter_elimination() {
         let optimizer = QueryOptimizer::new();
@@ -790,4 +1156,100 @@ mod tests {
         // Filter should be eliminated
         assert!(matches!(optimized.root, PlanNode::NodeScan { .. }));
     }
+
+    #[test]
+    fn test_filter_not_pushed_below_aggregate() {
+        let parser = crate::parser::QueryParser::new();
+        let planner = crate::planner::QueryPlanner::new();
+        let optimizer = QueryOptimizer::new();
+
+        let query = parser
+            .parse("MATCH (d:Dept) WITH d, count(*) AS c WHERE c > 5 RETURN d, c")
+            .unwrap();
+        let optimized = optimizer.optimize(planner.plan(&query).unwrap()).unwrap();
+
+        // Walk down to the Aggregate, requiring the Filter on the way
+        let mut node = &optimized.root;
+        let mut seen_filter = false;
+        loop {
+            match node {
+                PlanNode::Filter { input, .. } => {
+                    seen_filter = true;
+                    node = input;
+                }
+                PlanNode::Project { input, .. } => node = input,
+                PlanNode::Aggregate { input, .. } => {
+                    assert!(seen_filter, "filter was pushed below the aggregate");
+                    assert!(!matches!(**input, PlanNode::Filter { .. }));
+                    break;
+                }
+                other => panic!("unexpected node above aggregate: {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_filter_not_pushed_below_computed_alias() {
+        let optimizer = QueryOptimizer::new();
+
+        // WHERE total > 10 over `n.price * 2 AS total`
+        let plan = PlanNode::Filter {
+            input: Box::new(PlanNode::Project {
+                input: Box::new(PlanNode::NodeScan {
+                    variable: "n".to_string(),
+                    label: None,
+                }),
+                items: vec![(
+                    Expr::Binary {
+                        left: Box::new(Expr::Property {
+                            expr: Box::new(Expr::Variable("n".to_string())),
+                            name: "price".to_string(),
+                        }),
+                        op: BinaryOp::Mul,
+                        right: Box::new(Expr::Literal(Literal::Integer(2))),
+                    },
+                    "total".to_string(),
+                )],
+            }),
+            predicate: Expr::Binary {
+                left: Box::new(Expr::Variable("total".to_string())),
+                op: BinaryOp::Gt,
+                right: Box::new(Expr::Literal(Literal::Integer(10))),
+            },
+        };
+
+        let pushed = optimizer.push_down_predicates(plan).unwrap();
+        assert!(matches!(pushed, PlanNode::Filter { .. }));
+    }
+
+    #[test]
+    fn test_distinct_removed_over_grouped_aggregate() {
+        let parser = crate::parser::QueryParser::new();
+        let planner = crate::planner::QueryPlanner::new();
+        let optimizer = QueryOptimizer::new();
+
+        let query = parser
+            .parse("MATCH (n:Person) RETURN DISTINCT n.city, count(*) AS c")
+            .unwrap();
+        let optimized = optimizer.optimize(planner.plan(&query).unwrap()).unwrap();
+
+        match &optimized.root {
+            PlanNode::Project { input, .. } => {
+                assert!(matches!(**input, PlanNode::Aggregate { .. }));
+            }
+            other => panic!("expected Distinct to be removed, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_distinct_kept_over_scan() {
+        let parser = crate::parser::QueryParser::new();
+        let planner = crate::planner::QueryPlanner::new();
+        let optimizer = QueryOptimizer::new();
+
+        let query = parser.parse("MATCH (n:Person) RETURN DISTINCT n.city").unwrap();
+        let optimized = optimizer.optimize(planner.plan(&query).unwrap()).unwrap();
+
+        assert!(matches!(optimized.root, PlanNode::Distinct { .. }));
+    }
 }
@@ -10,6 +10,11 @@ use smallvec::SmallVec;
 use std::iter::Peekable;
 use std::str::CharIndices;
 
+/// Aggregate functions, the only calls that accept a DISTINCT modifier.
+///
+/// `count` has its own token and is handled by `parse_count`.
+const AGGREGATE_FUNCTIONS: [&str; 5] = ["sum", "avg", "min", "max", "collect"];
+
 /// Token types produced by the lexer.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token<'a> {
@@ -22,6 +27,7 @@ pub enum Token<'a> {
     Limit,
     Skip,
     Create,
+    Merge,
     Set,
     Delete,
     DetachDelete,
@@ -93,71 +99,102 @@ pub enum Token<'a> {
     Eof,
 }
 
+/// Comment syntax recognized by the lexer.
+///
+/// Disabling a comment form makes its markers lex as ordinary tokens, so
+/// with `line_comment: None` a `//` is two divisions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LexerOptions {
+    /// Marker starting a comment that runs to the end of the line
+    pub line_comment: Option<&'static str>,
+    /// Opening and closing markers of a block comment
+    pub block_comment: Option<(&'static str, &'static str)>,
+}
+
+impl Default for LexerOptions {
+    fn default() -> Self {
+        Self {
+            line_comment: Some("//"),
+            block_comment: Some(("/*", "*/")),
+        }
+    }
+}
+
 /// Streaming lexer for query strings.
 pub struct Lexer<'a> {
     input: &'a str,
     chars: Peekable<CharIndices<'a>>,
     position: usize,
+    /// Start of the most recently lexed token
+    token_start: usize,
+    options: LexerOptions,
 }
 
 impl<'a> Lexer<'a> {
     pub fn new(input: &'a str) -> Self {
+        Self::with_options(input, LexerOptions::default())
+    }
+
+    #[must_use]
+    pub fn with_options(input: &'a str, options: LexerOptions) -> Self {
         Self {
             input,
             chars: input.char_indices().peekable(),
             position: 0,
+            token_start: 0,
+            options,
         }
     }
 
+    /// Source text of the most recently lexed token.
+    pub(crate) fn token_text(&self) -> &'a str {
+        self.input
+            .get(self.token_start..self.position.max(self.token_start))
+            .unwrap_or("")
+    }
+
     fn peek_char(&mut self) -> Option<char> {
         self.chars.peek().map(|&(_, c)| c)
     }
 
     fn next_char(&mut self) -> Option<(usize, char)> {
         let result = self.chars.next();
-        if let Some((pos, _)) = result {
-            self.position = pos + 1;
+        if let Some((pos, c)) = result {
+            self.position = pos + c.len_utf8();
         }
         result
     }
 
-    fn skip_whitespace(&mut self) {
-        while let Some(c) = self.peek_char() {
-            if c.is_whitespace() {
-                self.next_char();
-            } else if c == '/' {
-                // Check for comments
-                let pos = self.position;
+    fn skip_whitespace(&mut self) -> Result<()> {
+        // Empty markers would match everywhere, so they count as disabled
+        let line_comment = self.options.line_comment.filter(|marker| !marker.is_empty());
+        let block_comment = self.options.block_comment.filter(|(open, _)| !open.is_empty());
+
+        loop {
+            let rest = &self.input[self.position..];
+            if rest.starts_with(char::is_whitespace) {
                 self.next_char();
-                if self.peek_char() == Some('/') {
-                    // Line comment
-                    while let Some(c) = self.peek_char() {
-                        if c == '\n' {
-                            break;
-                        }
-                        self.next_char();
-                    }
-                } else if self.peek_char() == Some('*') {
-                    // Block comment
+            } else if let Some(marker) = line_comment
+                && rest.starts_with(marker)
+            {
+                while self.peek_char().is_some_and(|c| c != '\n') {
+                    self.next_char();
+                }
+            } else if let Some((open, close)) = block_comment
+                && rest.starts_with(open)
+            {
+                let Some(len) = rest[open.len()..].find(close) else {
+                    return Err(QueryError::ParseError {
+                        position: self.position,
+                        message: "Unterminated block comment".to_string(),
+                    });
+                };
+                let end = self.position + open.len() + len + close.len();
+                while self.position < end {
                     self.next_char();
-                    loop {
-                        match self.next_char() {
-                            Some((_, '*')) if self.peek_char() == Some('/') => {
-                                self.next_char();
-                                break;
-                            }
-                            None => break,
-                            _ => {}
-                        }
-                    }
-                } else {
-                    // Not a comment, backtrack
-                    self.chars = self.input[pos..].char_indices().peekable();
-                    self.position = pos;
-                    break;
                 }
             } else {
-                break;
+                return Ok(());
             }
         }
     }
@@ -173,7 +210,7 @@ impl<'a> Lexer<'a> {
         &self.input[start..self.position]
     }
 
-    fn read_number(&mut self, start: usize) -> Token<'a> {
+    fn read_number(&mut self, start: usize) -> Result<Token<'a>> {
         let mut has_dot = false;
         let mut has_exp = false;
 
@@ -207,9 +244,18 @@ impl<'a> Lexer<'a> {
 
         let text = &self.input[start..self.position];
         if has_dot || has_exp {
-            Token::Float(text.parse().unwrap_or(0.0))
+            // An exponent marker needs digits, as in `1e` or `2E+`
+            let value = text.parse().map_err(|_| QueryError::ParseError {
+                position: start,
+                message: "malformed float literal".to_string(),
+            })?;
+            Ok(Token::Float(value))
         } else {
-            Token::Integer(text.parse().unwrap_or(0))
+            let value = text.parse().map_err(|_| QueryError::ParseError {
+                position: start,
+                message: "integer literal out of range".to_string(),
+            })?;
+            Ok(Token::Integer(value))
         }
     }
 
@@ -225,9 +271,10 @@ impl<'a> Lexer<'a> {
                 }
                 Some(_) => {}
                 None => {
+                    let what = if quote == '`' { "quoted identifier" } else { "string" };
                     return Err(QueryError::ParseError {
                         position: start,
-                        message: "Unterminated string".to_string(),
+                        message: format!("Unterminated {what}"),
                     });
                 }
             }
@@ -235,11 +282,13 @@ impl<'a> Lexer<'a> {
     }
 
     pub fn next_token(&mut self) -> Result<Token<'a>> {
-        self.skip_whitespace();
+        self.skip_whitespace()?;
 
         let Some((start, c)) = self.next_char() else {
+            self.token_start = self.input.len();
             return Ok(Token::Eof);
         };
+        self.token_start = start;
 
         match c {
             '(' => Ok(Token::LParen),
@@ -308,6 +357,12 @@ impl<'a> Lexer<'a> {
             }
             '$' => {
                 let ident = self.read_identifier(self.position);
+                if ident.is_empty() {
+                    return Err(QueryError::ParseError {
+                        position: start,
+                        message: "Expected parameter name after '$'".to_string(),
+                    });
+                }
                 Ok(Token::Parameter(ident))
             }
             '`' => {
@@ -318,7 +373,7 @@ impl<'a> Lexer<'a> {
                 let ident = self.read_identifier(start);
                 Ok(Self::keyword_or_ident(ident))
             }
-            _ if c.is_ascii_digit() => Ok(self.read_number(start)),
+            _ if c.is_ascii_digit() => self.read_number(start),
             _ => Err(QueryError::ParseError {
                 position: start,
                 message: format!("Unexpected character: {c}"),
@@ -326,7 +381,7 @@ impl<'a> Lexer<'a> {
         }
     }
 
-    fn keyword_or_ident(s: &str) -> Token<'_> {
+    pub(crate) fn keyword_or_ident(s: &str) -> Token<'_> {
         match s.to_uppercase().as_str() {
             "MATCH" => Token::Match,
             "OPTIONAL" => Token::OptionalMatch,
@@ -337,6 +392,7 @@ impl<'a> Lexer<'a> {
             "LIMIT" => Token::Limit,
             "SKIP" => Token::Skip,
             "CREATE" => Token::Create,
+            "MERGE" => Token::Merge,
             "SET" => Token::Set,
             "DELETE" => Token::Delete,
             "DETACH" => Token::DetachDelete,
@@ -373,7 +429,7 @@ impl<'a> Lexer<'a> {
 /// Query parser using recursive descent.
 #[derive(Debug, Default)]
 pub struct QueryParser {
-    // Parser configuration can be added here
+    options: LexerOptions,
 }
 
 impl QueryParser {
@@ -382,17 +438,29 @@ impl QueryParser {
         Self::default()
     }
 
+    /// Create a parser with custom lexer settings, e.g. comment syntax.
+    #[must_use]
+    pub fn with_options(options: LexerOptions) -> Self {
+        Self { options }
+    }
+
+    /// Lexer settings used by this parser.
+    #[must_use]
+    pub const fn options(&self) -> LexerOptions {
+        self.options
+    }
+
     /// Tokenize a query string into an iterator of tokens.
     pub fn tokenize<'a>(&self, query: &'a str) -> impl Iterator<Item = Token<'a>> + 'a {
         TokenIterator {
-            lexer: Lexer::new(query),
+            lexer: Lexer::with_options(query, self.options),
             done: false,
         }
     }
 
     /// Parse a query string into an AST.
     pub fn parse(&self, query: &str) -> Result<Query> {
-        let mut parser = Parser::new(query);
+        let mut parser = Parser::new(query, self.options);
         parser.parse_query()
     }
 }
@@ -426,20 +494,72 @@ impl<'a> Iterator for TokenIterator<'a> {
 struct Parser<'a> {
     lexer: Lexer<'a>,
     current: Token<'a>,
+    /// Source span of `current`
+    current_span: (usize, usize),
 }
 
 impl<'a> Parser<'a> {
-    fn new(input: &'a str) -> Self {
-        let mut lexer = Lexer::new(input);
+    fn new(input: &'a str, options: LexerOptions) -> Self {
+        let mut lexer = Lexer::with_options(input, options);
         let current = lexer.next_token().unwrap_or(Token::Eof);
-        Self { lexer, current }
+        let current_span = (lexer.token_start, lexer.position.max(lexer.token_start));
+        Self {
+            lexer,
+            current,
+            current_span,
+        }
     }
 
     fn advance(&mut self) -> Result<Token<'a>> {
         let prev = std::mem::replace(&mut self.current, self.lexer.next_token()?);
+        self.current_span = (
+            self.lexer.token_start,
+            self.lexer.position.max(self.lexer.token_start),
+        );
         Ok(prev)
     }
 
+    /// Source text of the current token.
+    fn current_text(&self) -> &'a str {
+        let (start, end) = self.current_span;
+        self.lexer.input.get(start..end).unwrap_or("")
+    }
+
+    /// Whether the current token is an identifier or keyword.
+    fn current_is_word(&self) -> bool {
+        self.current_text()
+            .starts_with(|c: char| c.is_alphabetic() || c == '_')
+    }
+
+    /// Reject a keyword in a variable position with a hint to backtick-quote it.
+    fn reject_keyword_variable(&self) -> Result<()> {
+        let text = self.current_text();
+        if self.current_is_word() && !matches!(self.current, Token::Ident(_)) {
+            return Err(QueryError::ParseError {
+                position: self.current_span.0,
+                message: format!(
+                    "'{text}' is a reserved keyword and cannot be used as a variable name; \
+                     quote it with backticks: `{text}`"
+                ),
+            });
+        }
+        Ok(())
+    }
+
+    /// Consume a property or map key, if the current token can be one.
+    ///
+    /// Keywords count by their source text, so `n.merge` and `{on: 1}`
+    /// name properties like any other word.
+    fn parse_property_key(&mut self) -> Result<Option<String>> {
+        let key = match self.current {
+            Token::Ident(name) => name.to_string(),
+            _ if self.current_is_word() => self.current_text().to_string(),
+            _ => return Ok(None),
+        };
+        self.advance()?;
+        Ok(Some(key))
+    }
+
     fn expect(&mut self, expected: Token<'_>) -> Result<()> {
         if std::mem::discriminant(&self.current) == std::mem::discriminant(&expected) {
             self.advance()?;
@@ -465,6 +585,7 @@ impl<'a> Parser<'a> {
                 Token::Limit => clauses.push(self.parse_limit()?),
                 Token::Skip => clauses.push(self.parse_skip()?),
                 Token::Create => clauses.push(self.parse_create()?),
+                Token::Merge => clauses.push(self.parse_merge()?),
                 Token::With => clauses.push(self.parse_with()?),
                 Token::Eof => break,
                 _ => {
@@ -602,6 +723,61 @@ impl<'a> Parser<'a> {
         Ok(Clause::Create(CreateClause { pattern }))
     }
 
+    fn parse_merge(&mut self) -> Result<Clause> {
+        self.expect(Token::Merge)?;
+        // MERGE takes a single path
+        let pattern = Pattern {
+            paths: vec![self.parse_path_pattern()?],
+        };
+
+        // ON is only special here, so `on` stays usable as a name elsewhere
+        let mut on_create = Vec::new();
+        let mut on_match = Vec::new();
+        while matches!(self.current, Token::Ident(_))
+            && self.current_text().eq_ignore_ascii_case("ON")
+        {
+            self.advance()?;
+            let items = match self.current {
+                Token::Create => &mut on_create,
+                Token::Match => &mut on_match,
+                _ => {
+                    return Err(QueryError::ParseError {
+                        position: self.lexer.position,
+                        message: format!(
+                            "Expected CREATE or MATCH after ON, found {:?}",
+                            self.current
+                        ),
+                    });
+                }
+            };
+            self.advance()?;
+            self.expect(Token::Set)?;
+            items.extend(self.parse_set_items()?);
+        }
+
+        Ok(Clause::Merge(MergeClause {
+            pattern,
+            on_create,
+            on_match,
+        }))
+    }
+
+    fn parse_set_items(&mut self) -> Result<Vec<SetItem>> {
+        let mut items = vec![self.parse_set_item()?];
+        while matches!(self.current, Token::Comma) {
+            self.advance()?;
+            items.push(self.parse_set_item()?);
+        }
+        Ok(items)
+    }
+
+    fn parse_set_item(&mut self) -> Result<SetItem> {
+        let target = self.parse_postfix_expression()?;
+        self.expect(Token::Eq)?;
+        let value = self.parse_expression()?;
+        Ok(SetItem { target, value })
+    }
+
     fn parse_with(&mut self) -> Result<Clause> {
         self.expect(Token::With)?;
         let distinct = matches!(self.current, Token::Distinct);
@@ -646,6 +822,7 @@ impl<'a> Parser<'a> {
         let mut node = NodePattern::default();
 
         // Variable name
+        self.reject_keyword_variable()?;
         if let Token::Ident(name) = &self.current {
             node.variable = Some((*name).to_string());
             self.advance()?;
@@ -690,20 +867,23 @@ impl<'a> Parser<'a> {
             self.advance()?;
 
             // Variable
+            self.reject_keyword_variable()?;
             if let Token::Ident(name) = &self.current {
                 edge.variable = Some((*name).to_string());
                 self.advance()?;
             }
 
-            // Types
-            while matches!(self.current, Token::Colon) {
+            // Types: `:A|B` or `:A|:B`
+            if matches!(self.current, Token::Colon) {
                 self.advance()?;
-                if let Token::Ident(rel_type) = &self.current {
-                    edge.rel_types.push((*rel_type).to_string());
-                    self.advance()?;
-                }
-                if matches!(self.current, Token::Pipe) {
+                edge.rel_types.push(self.parse_rel_type()?);
+
+                while matches!(self.current, Token::Pipe) {
                     self.advance()?;
+                    if matches!(self.current, Token::Colon) {
+                        self.advance()?;
+                    }
+                    edge.rel_types.push(self.parse_rel_type()?);
                 }
             }
 
@@ -736,47 +916,72 @@ impl<'a> Parser<'a> {
         Ok(edge)
     }
 
+    fn parse_rel_type(&mut self) -> Result<String> {
+        if let Token::Ident(rel_type) = &self.current {
+            let rel_type = (*rel_type).to_string();
+            self.advance()?;
+            Ok(rel_type)
+        } else {
+            Err(QueryError::ParseError {
+                position: self.lexer.position,
+                message: format!("Expected relationship type, found {:?}", self.current),
+            })
+        }
+    }
+
     fn parse_length_spec(&mut self) -> Result<LengthSpec> {
+        let position = self.lexer.position;
         let mut spec = LengthSpec {
             min: None,
             max: None,
         };
 
-        if let Token::Integer(n) = self.current {
-            spec.min = Some(n as u32);
-            self.advance()?;
+        if let Some(n) = self.parse_length_bound()? {
+            spec.min = Some(n);
 
             if matches!(self.current, Token::DoubleDot) {
                 self.advance()?;
-                if let Token::Integer(m) = self.current {
-                    spec.max = Some(m as u32);
-                    self.advance()?;
-                }
+                spec.max = self.parse_length_bound()?;
             } else {
                 spec.max = spec.min;
             }
         } else if matches!(self.current, Token::DoubleDot) {
             self.advance()?;
-            if let Token::Integer(m) = self.current {
-                spec.max = Some(m as u32);
-                self.advance()?;
-            }
+            spec.max = self.parse_length_bound()?;
+        }
+
+        if let (Some(min), Some(max)) = (spec.min, spec.max)
+            && min > max
+        {
+            return Err(QueryError::ParseError {
+                position,
+                message: format!("Invalid path length *{min}..{max}: minimum exceeds maximum"),
+            });
         }
 
         Ok(spec)
     }
 
+    fn parse_length_bound(&mut self) -> Result<Option<u32>> {
+        if let Token::Integer(n) = self.current {
+            let bound = u32::try_from(n).map_err(|_| QueryError::ParseError {
+                position: self.lexer.position,
+                message: format!("Path length {n} out of range"),
+            })?;
+            self.advance()?;
+            Ok(Some(bound))
+        } else {
+            Ok(None)
+        }
+    }
+
     fn parse_map_literal(&mut self) -> Result<IndexMap<String, Expr>> {
         self.expect(Token::LBrace)?;
         let mut map = IndexMap::new();
 
         if !matches!(self.current, Token::RBrace) {
             loop {
-                let key = if let Token::Ident(name) = &self.current {
-                    let k = (*name).to_string();
-                    self.advance()?;
-                    k
-                } else {
+                let Some(key) = self.parse_property_key()? else {
                     return Err(QueryError::ParseError {
                         position: self.lexer.position,
                         message: "Expected property name".to_string(),
@@ -909,7 +1114,8 @@ impl<'a> Parser<'a> {
         loop {
             let op = match &self.current {
                 Token::Plus => BinaryOp::Add,
-                Token::Minus => BinaryOp::Sub,
+                // The lexer cannot tell subtraction from a pattern dash
+                Token::Minus | Token::Dash => BinaryOp::Sub,
                 _ => break,
             };
             self.advance()?;
@@ -960,13 +1166,19 @@ impl<'a> Parser<'a> {
 
     fn parse_unary_expression(&mut self) -> Result<Expr> {
         match &self.current {
-            Token::Minus => {
+            Token::Minus | Token::Dash => {
                 self.advance()?;
-                let expr = self.parse_unary_expression()?;
-                Ok(Expr::Unary {
-                    op: UnaryOp::Neg,
-                    expr: Box::new(expr),
-                })
+                // Fold negative numbers into a single literal
+                match self.parse_unary_expression()? {
+                    Expr::Literal(Literal::Integer(n)) if n != i64::MIN => {
+                        Ok(Expr::Literal(Literal::Integer(-n)))
+                    }
+                    Expr::Literal(Literal::Float(f)) => Ok(Expr::Literal(Literal::Float(-f))),
+                    expr => Ok(Expr::Unary {
+                        op: UnaryOp::Neg,
+                        expr: Box::new(expr),
+                    }),
+                }
             }
             Token::Plus => {
                 self.advance()?;
@@ -987,9 +1199,7 @@ impl<'a> Parser<'a> {
             match &self.current {
                 Token::Dot => {
                     self.advance()?;
-                    if let Token::Ident(name) = &self.current {
-                        let name = (*name).to_string();
-                        self.advance()?;
+                    if let Some(name) = self.parse_property_key()? {
                         expr = Expr::Property {
                             expr: Box::new(expr),
                             name,
@@ -1017,6 +1227,78 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
+    /// Consume an optional DISTINCT at the start of a function's arguments.
+    fn parse_distinct_modifier(&mut self) -> Result<bool> {
+        if matches!(self.current, Token::Distinct) {
+            self.advance()?;
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }
+
+    /// Parse the arguments of a call to `name`, starting at the `(`.
+    ///
+    /// DISTINCT is only accepted in aggregate functions, with an argument.
+    fn parse_function_call(&mut self, name: String) -> Result<Expr> {
+        self.expect(Token::LParen)?;
+        let distinct = self.parse_distinct_modifier()?;
+        let is_aggregate = AGGREGATE_FUNCTIONS.iter().any(|f| f.eq_ignore_ascii_case(&name));
+        if distinct && !is_aggregate {
+            return Err(QueryError::ParseError {
+                position: self.lexer.position,
+                message: format!("DISTINCT is not allowed in {name}()"),
+            });
+        }
+        let mut args = Vec::new();
+        if !matches!(self.current, Token::RParen) {
+            args.push(self.parse_expression()?);
+            while matches!(self.current, Token::Comma) {
+                self.advance()?;
+                args.push(self.parse_expression()?);
+            }
+        } else if distinct {
+            return Err(QueryError::ParseError {
+                position: self.lexer.position,
+                message: "Expected expression after DISTINCT".to_string(),
+            });
+        }
+        self.expect(Token::RParen)?;
+        Ok(Expr::FunctionCall {
+            name,
+            args,
+            distinct,
+        })
+    }
+
+    /// Parse `count(expr)`, `count(*)` or a `count { pattern }` subquery.
+    fn parse_count(&mut self) -> Result<Expr> {
+        self.expect(Token::Count)?;
+
+        if matches!(self.current, Token::LParen) {
+            self.advance()?;
+            // count(*) carries no arguments
+            let (args, distinct) = if matches!(self.current, Token::Star) {
+                self.advance()?;
+                (Vec::new(), false)
+            } else {
+                let distinct = self.parse_distinct_modifier()?;
+                (vec![self.parse_expression()?], distinct)
+            };
+            self.expect(Token::RParen)?;
+            return Ok(Expr::FunctionCall {
+                name: "count".to_string(),
+                args,
+                distinct,
+            });
+        }
+
+        self.expect(Token::LBrace)?;
+        let pattern = self.parse_pattern()?;
+        self.expect(Token::RBrace)?;
+        Ok(Expr::Count { pattern })
+    }
+
     fn parse_primary_expression(&mut self) -> Result<Expr> {
         match &self.current {
             Token::Null => {
@@ -1057,17 +1339,7 @@ impl<'a> Parser<'a> {
 
                 // Check for function call
                 if matches!(self.current, Token::LParen) {
-                    self.advance()?;
-                    let mut args = Vec::new();
-                    if !matches!(self.current, Token::RParen) {
-                        args.push(self.parse_expression()?);
-                        while matches!(self.current, Token::Comma) {
-                            self.advance()?;
-                            args.push(self.parse_expression()?);
-                        }
-                    }
-                    self.expect(Token::RParen)?;
-                    Ok(Expr::FunctionCall { name, args })
+                    self.parse_function_call(name)
                 } else {
                     Ok(Expr::Variable(name))
                 }
@@ -1095,13 +1367,7 @@ impl<'a> Parser<'a> {
                 let map = self.parse_map_literal()?;
                 Ok(Expr::Map(map))
             }
-            Token::Count => {
-                self.advance()?;
-                self.expect(Token::LBrace)?;
-                let pattern = self.parse_pattern()?;
-                self.expect(Token::RBrace)?;
-                Ok(Expr::Count { pattern })
-            }
+            Token::Count => self.parse_count(),
             Token::Exists => {
                 self.advance()?;
                 self.expect(Token::LBrace)?;
@@ -1159,6 +1425,401 @@ mod tests {
         assert!(query.clauses.len() >= 3);
     }
 
+    fn first_edge(query: &Query) -> &EdgePattern {
+        let Clause::Match(m) = &query.clauses[0] else {
+            panic!("expected MATCH clause");
+        };
+        m.pattern.paths[0]
+            .elements
+            .iter()
+            .find_map(|e| match e {
+                PathElement::Edge(edge) => Some(edge),
+                PathElement::Node(_) => None,
+            })
+            .expect("expected an edge")
+    }
+
+    #[test]
+    fn test_edge_type_disjunction_with_length() {
+        let parser = QueryParser::new();
+        let query = parser
+            .parse("MATCH (a)-[:KNOWS|FOLLOWS*2..4]->(b) RETURN b")
+            .unwrap();
+        let edge = first_edge(&query);
+
+        assert_eq!(edge.rel_types.as_slice(), ["KNOWS", "FOLLOWS"]);
+        assert_eq!(
+            edge.length,
+            Some(LengthSpec {
+                min: Some(2),
+                max: Some(4)
+            })
+        );
+        assert_eq!(edge.direction, Direction::Outgoing);
+    }
+
+    #[test]
+    fn test_edge_type_disjunction_repeated_colon() {
+        let parser = QueryParser::new();
+        let query = parser
+            .parse("MATCH (a)-[r:KNOWS|:FOLLOWS|LIKES]-(b) RETURN r")
+            .unwrap();
+        let edge = first_edge(&query);
+
+        assert_eq!(edge.variable.as_deref(), Some("r"));
+        assert_eq!(edge.rel_types.as_slice(), ["KNOWS", "FOLLOWS", "LIKES"]);
+        assert_eq!(edge.length, None);
+    }
+
+    #[test]
+    fn test_edge_length_variants() {
+        let parser = QueryParser::new();
+
+        let query = parser.parse("MATCH (a)-[:KNOWS*3]->(b) RETURN b").unwrap();
+        assert_eq!(
+            first_edge(&query).length,
+            Some(LengthSpec {
+                min: Some(3),
+                max: Some(3)
+            })
+        );
+
+        let query = parser.parse("MATCH (a)-[*..5]->(b) RETURN b").unwrap();
+        assert_eq!(
+            first_edge(&query).length,
+            Some(LengthSpec {
+                min: None,
+                max: Some(5)
+            })
+        );
+    }
+
+    #[test]
+    fn test_edge_length_min_exceeds_max() {
+        let parser = QueryParser::new();
+        let err = parser
+            .parse("MATCH (a)-[:KNOWS*3..1]->(b) RETURN b")
+            .unwrap_err();
+        assert!(matches!(err, QueryError::ParseError { .. }));
+    }
+
+    #[test]
+    fn test_integer_literal_out_of_range() {
+        let parser = QueryParser::new();
+        for query in [
+            "MATCH (n) RETURN 99999999999999999999",
+            "MATCH (a)-[*99999999999999999999]->(b) RETURN b",
+        ] {
+            let err = parser.parse(query).unwrap_err();
+            assert!(
+                matches!(&err, QueryError::ParseError { message, .. }
+                    if message == "integer literal out of range"),
+                "{query}: {err:?}"
+            );
+        }
+        assert!(parser.parse("MATCH (n) RETURN 9223372036854775807").is_ok());
+    }
+
+    #[test]
+    fn test_edge_dangling_pipe() {
+        let parser = QueryParser::new();
+        assert!(parser.parse("MATCH (a)-[:KNOWS|]->(b) RETURN b").is_err());
+    }
+
+    #[test]
+    fn test_merge_with_on_create_and_on_match() {
+        let parser = QueryParser::new();
+        let query = parser
+            .parse(
+                "MERGE (n:Person {id: 1})
+                 ON CREATE SET n.created = true, n.visits = 1
+                 ON MATCH SET n.visits = n.visits + 1
+                 RETURN n",
+            )
+            .unwrap();
+        assert_eq!(query.clauses.len(), 2);
+
+        let Clause::Merge(merge) = &query.clauses[0] else {
+            panic!("expected MERGE clause");
+        };
+        assert_eq!(merge.pattern.paths.len(), 1);
+        assert_eq!(merge.on_create.len(), 2);
+        assert_eq!(merge.on_match.len(), 1);
+        assert!(matches!(
+            &merge.on_match[0].target,
+            Expr::Property { name, .. } if name == "visits"
+        ));
+    }
+
+    #[test]
+    fn test_merge_rejects_bad_on_action() {
+        let parser = QueryParser::new();
+        assert!(parser.parse("MERGE (n:Person) ON DELETE SET n.x = 1").is_err());
+    }
+
+    #[test]
+    fn test_merge_keywords_usable_as_names() {
+        let parser = QueryParser::new();
+        let query = parser
+            .parse("MATCH (on {on: 1, merge: 2}) RETURN on.on, on.merge, on.MATCH")
+            .unwrap();
+
+        let Clause::Match(m) = &query.clauses[0] else {
+            panic!("expected MATCH clause");
+        };
+        let PathElement::Node(node) = &m.pattern.paths[0].elements[0] else {
+            panic!("expected node");
+        };
+        assert_eq!(node.variable.as_deref(), Some("on"));
+        assert_eq!(node.properties.keys().collect::<Vec<_>>(), ["on", "merge"]);
+
+        let Clause::Return(ret) = &query.clauses[1] else {
+            panic!("expected RETURN clause");
+        };
+        let names: Vec<&str> = ret
+            .items
+            .iter()
+            .map(|item| match &item.expr {
+                Expr::Property { name, .. } => name.as_str(),
+                other => panic!("expected property, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(names, ["on", "merge", "MATCH"]);
+
+        // Still an ON action right after a MERGE pattern
+        let query = parser.parse("MERGE (n) on create SET n.on = 1").unwrap();
+        let Clause::Merge(merge) = &query.clauses[0] else {
+            panic!("expected MERGE clause");
+        };
+        assert_eq!(merge.on_create.len(), 1);
+    }
+
+    #[test]
+    fn test_function_call_distinct() {
+        let parser = QueryParser::new();
+        let query = parser
+            .parse("MATCH (n) RETURN count(DISTINCT n.dept), collect(DISTINCT n.name), sum(n.age)")
+            .unwrap();
+        let Clause::Return(ret) = &query.clauses[1] else {
+            panic!("expected RETURN clause");
+        };
+
+        let flags: Vec<(&str, bool)> = ret
+            .items
+            .iter()
+            .map(|item| match &item.expr {
+                Expr::FunctionCall { name, distinct, .. } => (name.as_str(), *distinct),
+                other => panic!("expected function call, got {other:?}"),
+            })
+            .collect();
+        assert_eq!(flags, [("count", true), ("collect", true), ("sum", false)]);
+    }
+
+    #[test]
+    fn test_function_call_distinct_misplaced() {
+        let parser = QueryParser::new();
+        for query in [
+            "MATCH (n) RETURN toUpper(DISTINCT n.name)",
+            "MATCH (n) RETURN f(DISTINCT)",
+            "MATCH (n) RETURN collect(DISTINCT)",
+            "MATCH (n) RETURN count(DISTINCT)",
+        ] {
+            let err = parser.parse(query).unwrap_err();
+            assert!(matches!(err, QueryError::ParseError { .. }), "{query}: {err:?}");
+        }
+        assert!(parser.parse("MATCH (n) RETURN MAX(DISTINCT n.age)").is_ok());
+    }
+
+    #[test]
+    fn test_negative_and_scientific_map_values() {
+        let parser = QueryParser::new();
+        let query = parser
+            .parse("MATCH (n {temp: -5, scale: 1.2e-3, drift: -2.5E2}) RETURN n")
+            .unwrap();
+        let Clause::Match(m) = &query.clauses[0] else {
+            panic!("expected MATCH clause");
+        };
+        let PathElement::Node(node) = &m.pattern.paths[0].elements[0] else {
+            panic!("expected node pattern");
+        };
+
+        assert_eq!(node.properties.get("temp"), Some(&Expr::Literal(Literal::Integer(-5))));
+        assert_eq!(node.properties.get("scale"), Some(&Expr::Literal(Literal::Float(1.2e-3))));
+        assert_eq!(node.properties.get("drift"), Some(&Expr::Literal(Literal::Float(-250.0))));
+    }
+
+    #[test]
+    fn test_subtraction_and_negation() {
+        let parser = QueryParser::new();
+        let query = parser.parse("MATCH (n) RETURN 10 - 3 - n.x, -n.y").unwrap();
+        let Clause::Return(ret) = &query.clauses[1] else {
+            panic!("expected RETURN clause");
+        };
+
+        // Left-associative: (10 - 3) - n.x
+        let Expr::Binary { left, op, .. } = &ret.items[0].expr else {
+            panic!("expected subtraction, got {:?}", ret.items[0].expr);
+        };
+        assert_eq!(*op, BinaryOp::Sub);
+        assert!(matches!(**left, Expr::Binary { op: BinaryOp::Sub, .. }));
+
+        assert!(matches!(
+            ret.items[1].expr,
+            Expr::Unary {
+                op: UnaryOp::Neg,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_keyword_as_variable_suggests_backticks() {
+        let parser = QueryParser::new();
+
+        for query in ["MATCH (count) RETURN 1", "MATCH (a)-[match]->(b) RETURN a"] {
+            let err = parser.parse(query).unwrap_err().to_string();
+            assert!(err.contains("reserved keyword"), "{query}: {err}");
+            assert!(err.contains("backticks"), "{query}: {err}");
+        }
+
+        let err = parser.parse("MATCH (Count) RETURN 1").unwrap_err().to_string();
+        assert!(err.contains("`Count`"), "{err}");
+
+        // Quoted keywords are plain identifiers
+        let query = parser.parse("MATCH (`count`) RETURN `count`").unwrap();
+        assert_eq!(query.clauses.len(), 2);
+    }
+
+    #[test]
+    fn test_return_list_and_map_literals() {
+        let parser = QueryParser::new();
+
+        let query = parser.parse("RETURN [1, 2, 3] AS xs").unwrap();
+        let Clause::Return(ret) = &query.clauses[0] else {
+            panic!("expected RETURN, got {:?}", query.clauses[0]);
+        };
+        assert_eq!(ret.items[0].alias.as_deref(), Some("xs"));
+        let Expr::List(elements) = &ret.items[0].expr else {
+            panic!("expected list, got {:?}", ret.items[0].expr);
+        };
+        assert_eq!(elements.len(), 3);
+
+        // A map literal in RETURN is an expression, not a node pattern
+        let query = parser.parse("RETURN {a: 1, b: 2} AS m").unwrap();
+        let Clause::Return(ret) = &query.clauses[0] else {
+            panic!("expected RETURN, got {:?}", query.clauses[0]);
+        };
+        assert_eq!(ret.items[0].alias.as_deref(), Some("m"));
+        let Expr::Map(map) = &ret.items[0].expr else {
+            panic!("expected map, got {:?}", ret.items[0].expr);
+        };
+        assert_eq!(map.keys().collect::<Vec<_>>(), ["a", "b"]);
+        assert!(matches!(map.get("b"), Some(Expr::Literal(Literal::Integer(2)))));
+
+        // Empty literals and nesting
+        let query = parser.parse("RETURN [], {}, [{a: [1]}]").unwrap();
+        let Clause::Return(ret) = &query.clauses[0] else {
+            panic!("expected RETURN, got {:?}", query.clauses[0]);
+        };
+        assert!(matches!(&ret.items[0].expr, Expr::List(l) if l.is_empty()));
+        assert!(matches!(&ret.items[1].expr, Expr::Map(m) if m.is_empty()));
+        assert!(matches!(&ret.items[2].expr, Expr::List(l) if matches!(l[0], Expr::Map(_))));
+    }
+
+    #[test]
+    fn test_malformed_input_is_a_parse_error() {
+        let parser = QueryParser::new();
+
+        for (query, expected) in [
+            ("RETURN 'abc", "Unterminated string"),
+            ("RETURN 'abc\\", "Unterminated string"),
+            ("MATCH (n) /* never closed RETURN n", "Unterminated block comment"),
+            ("MATCH (n) WHERE n.id = $ RETURN n", "Expected parameter name"),
+            ("MATCH (`n) RETURN n", "Unterminated quoted identifier"),
+            ("RETURN `", "Unterminated quoted identifier"),
+            ("RETURN 1 #", "Unexpected character"),
+            ("RETURN 1e", "malformed float literal"),
+            ("RETURN 2E+", "malformed float literal"),
+        ] {
+            match parser.parse(query) {
+                Err(QueryError::ParseError { message, .. }) => {
+                    assert!(message.contains(expected), "{query}: {message}");
+                }
+                other => panic!("{query}: expected parse error, got {other:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_lexer_slices_on_char_boundaries() {
+        let parser = QueryParser::new();
+
+        // Multi-byte identifiers and a lone '/' that is not a comment
+        let query = parser.parse("MATCH (café:Ünïcode) RETURN café.n / 2").unwrap();
+        assert_eq!(query.clauses.len(), 2);
+
+        let tokens: Vec<_> = parser.tokenize("RETURN 4 / 2 // done").collect();
+        assert!(matches!(
+            tokens[..],
+            [Token::Return, Token::Integer(4), Token::Slash, Token::Integer(2), Token::Eof]
+        ));
+    }
+
+    #[test]
+    fn test_arbitrary_input_never_panics() {
+        const ALPHABET: &[&str] = &[
+            "MATCH", "RETURN", "WHERE", "(", ")", "[", "]", "{", "}", "-", ">", "<", "*", "/",
+            ".", "..", ":", ",", "'", "\"", "`", "$", "\\", "n", "é", "1", "2.5", "e", " ",
+            "\n", "|", "=", "count", "exists",
+        ];
+
+        let parser = QueryParser::new();
+        // Fixed-seed LCG keeps the corpus deterministic
+        let mut state: u64 = 0x2545_f491_4f6c_dd1d;
+        for _ in 0..5000 {
+            let mut query = String::new();
+            state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+            for _ in 0..(state >> 59) {
+                state = state.wrapping_mul(6_364_136_223_846_793_005).wrapping_add(1);
+                #[allow(clippy::cast_possible_truncation)]
+                query.push_str(ALPHABET[(state >> 33) as usize % ALPHABET.len()]);
+            }
+
+            let _ = parser.parse(&query);
+            assert!(parser.tokenize(&query).count() <= query.len() + 1, "{query}");
+        }
+    }
+
+    #[test]
+    fn test_configurable_comment_syntax() {
+        let no_comments = QueryParser::with_options(LexerOptions {
+            line_comment: None,
+            block_comment: None,
+        });
+
+        let tokens: Vec<_> = no_comments.tokenize("a // b").collect();
+        assert!(matches!(
+            tokens[..],
+            [Token::Ident("a"), Token::Slash, Token::Slash, Token::Ident("b"), Token::Eof]
+        ));
+        let tokens: Vec<_> = no_comments.tokenize("8 /* 2 */").collect();
+        assert!(matches!(
+            tokens[..],
+            [Token::Integer(8), Token::Slash, Token::Star, Token::Integer(2), Token::Star, ..]
+        ));
+
+        // Custom markers replace the defaults
+        let hash_comments = QueryParser::with_options(LexerOptions {
+            line_comment: Some("#"),
+            block_comment: Some(("(:", ":)")),
+        });
+        let query = hash_comments
+            .parse("MATCH (n) (: pick all :) RETURN n # trailing // note")
+            .unwrap();
+        assert_eq!(query.clauses.len(), 2);
+        assert!(hash_comments.parse("RETURN 1 // 2").is_err());
+    }
+
     #[test]
     fn test_tokenizer() {
         let parser = QueryParser::new();
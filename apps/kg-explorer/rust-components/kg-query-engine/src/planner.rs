@@ -7,6 +7,13 @@ use crate::ast::*;
 use crate::{QueryError, Result};
 use serde::{Deserialize, Serialize};
 
+/// Prefix of variables generated for anonymous pattern elements.
+///
+/// Unquoted identifiers cannot contain spaces, so only a backtick-quoted
+/// name can spell one of these; a query that does refers to the generated
+/// element, as the lifting tests below do on purpose.
+const ANON_PREFIX: &str = "  UNNAMED";
+
 /// A logical execution plan for a query.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionPlan {
@@ -109,7 +116,8 @@ pub enum PlanNode {
     Aggregate {
         input: Box<PlanNode>,
         group_by: Vec<Expr>,
-        aggregates: Vec<(AggregateOp, Expr, String)>,
+        /// (op, argument, output alias, DISTINCT)
+        aggregates: Vec<(AggregateOp, Expr, String, bool)>,
     },
 
     /// Join two inputs
@@ -146,6 +154,14 @@ pub enum PlanNode {
         pattern: Pattern,
     },
 
+    /// Match a pattern or create it if missing, then apply the matching SET items
+    Merge {
+        input: Box<PlanNode>,
+        pattern: Pattern,
+        on_create: Vec<(Expr, Expr)>,
+        on_match: Vec<(Expr, Expr)>,
+    },
+
     /// Set properties
     SetProperty {
         input: Box<PlanNode>,
@@ -204,6 +220,7 @@ impl QueryPlanner {
     pub fn plan(&self, query: &Query) -> Result<ExecutionPlan> {
         let mut plan = PlanNode::SingleRow;
         let mut required_indexes = Vec::new();
+        let query = self.lift_property_maps(query, &mut required_indexes);
 
         for clause in &query.clauses {
             plan = self.plan_clause(clause, plan, &mut required_indexes)?;
@@ -240,8 +257,14 @@ impl QueryPlanner {
                 input: Box::new(input),
                 pattern: c.pattern.clone(),
             }),
+            Clause::Merge(m) => Ok(PlanNode::Merge {
+                input: Box::new(input),
+                pattern: m.pattern.clone(),
+                on_create: Self::set_pairs(&m.on_create),
+                on_match: Self::set_pairs(&m.on_match),
+            }),
             Clause::Set(s) => {
-                let items = s.items.iter().map(|i| (i.target.clone(), i.value.clone())).collect();
+                let items = Self::set_pairs(&s.items);
                 Ok(PlanNode::SetProperty {
                     input: Box::new(input),
                     items,
@@ -262,6 +285,10 @@ impl QueryPlanner {
         }
     }
 
+    fn set_pairs(items: &[SetItem]) -> Vec<(Expr, Expr)> {
+        items.iter().map(|i| (i.target.clone(), i.value.clone())).collect()
+    }
+
     fn plan_match(
         &self,
         match_clause: &MatchClause,
@@ -307,15 +334,7 @@ impl QueryPlanner {
                         let label = node.labels.first().cloned();
 
                         // Check if we can use an index
-                        if let (Some(label), Some((prop, _value))) =
-                            (label.as_ref(), node.properties.iter().next())
-                        {
-                            indexes.push(IndexRequirement {
-                                label: label.clone(),
-                                property: prop.clone(),
-                                index_type: IndexType::BTree,
-                            });
-                        }
+                        indexes.extend(Self::index_hint(node));
 
                         current = if matches!(current, PlanNode::SingleRow) {
                             PlanNode::NodeScan {
@@ -405,6 +424,89 @@ impl QueryPlanner {
         Ok(current)
     }
 
+    /// Rewrite the inline property maps of each non-optional MATCH into an
+    /// explicit WHERE, so they are planned like any other predicate.
+    ///
+    /// `MATCH (n:Person {age: 30})` becomes `MATCH (n:Person) WHERE n.age = 30`,
+    /// merged with the MATCH's own WHERE if it has one. Anonymous elements
+    /// with properties get generated names, numbered across the whole clause.
+    /// OPTIONAL MATCH keeps its maps, since a WHERE after it filters rows
+    /// rather than constraining the optional pattern. Variable-length edges
+    /// keep theirs too: the map constrains every relationship on the path,
+    /// which a predicate on the relationship list cannot express.
+    fn lift_property_maps(&self, query: &Query, indexes: &mut Vec<IndexRequirement>) -> Query {
+        let mut clauses = Vec::with_capacity(query.clauses.len());
+        let mut rest = query.clauses.iter().peekable();
+        // Numbered across the whole query, so clauses never share a name
+        let mut anonymous = 0;
+
+        while let Some(clause) = rest.next() {
+            let Clause::Match(m) = clause else {
+                clauses.push(clause.clone());
+                continue;
+            };
+            if m.optional {
+                clauses.push(clause.clone());
+                continue;
+            }
+
+            let mut m = m.clone();
+            let mut predicates = Vec::new();
+            for path in &mut m.pattern.paths {
+                if let Some(PathElement::Node(first)) = path.elements.first() {
+                    indexes.extend(Self::index_hint(first));
+                }
+
+                for element in &mut path.elements {
+                    let (variable, properties) = match element {
+                        PathElement::Node(n) => (&mut n.variable, &mut n.properties),
+                        PathElement::Edge(e) if e.length.is_none() => {
+                            (&mut e.variable, &mut e.properties)
+                        }
+                        PathElement::Edge(_) => continue,
+                    };
+                    if properties.is_empty() {
+                        continue;
+                    }
+                    let properties = std::mem::take(properties);
+                    let var = variable.get_or_insert_with(|| {
+                        let name = format!("{ANON_PREFIX}{anonymous}");
+                        anonymous += 1;
+                        name
+                    });
+                    predicates.push(self.properties_to_predicate(var, &properties));
+                }
+            }
+            clauses.push(Clause::Match(m));
+
+            if let Some(Clause::Where(w)) = rest.peek() {
+                predicates.push(w.predicate.clone());
+                rest.next();
+            }
+            let predicate = predicates.into_iter().reduce(|acc, pred| Expr::Binary {
+                left: Box::new(acc),
+                op: BinaryOp::And,
+                right: Box::new(pred),
+            });
+            if let Some(predicate) = predicate {
+                clauses.push(Clause::Where(WhereClause { predicate }));
+            }
+        }
+
+        Query { clauses }
+    }
+
+    /// Index that would serve the inline property filter of a scanned node.
+    fn index_hint(node: &NodePattern) -> Option<IndexRequirement> {
+        let label = node.labels.first()?;
+        let (property, _value) = node.properties.iter().next()?;
+        Some(IndexRequirement {
+            label: label.clone(),
+            property: property.clone(),
+            index_type: IndexType::BTree,
+        })
+    }
+
     fn properties_to_predicate(
         &self,
         var: &str,
@@ -444,42 +546,7 @@ impl QueryPlanner {
     }
 
     fn plan_return(&self, return_clause: &ReturnClause, input: PlanNode) -> Result<PlanNode> {
-        let items: Vec<(Expr, String)> = return_clause
-            .items
-            .iter()
-            .enumerate()
-            .map(|(i, item)| {
-                let alias = item
-                    .alias
-                    .clone()
-                    .unwrap_or_else(|| self.expr_to_name(&item.expr, i));
-                (item.expr.clone(), alias)
-            })
-            .collect();
-
-        let plan = PlanNode::Project {
-            input: Box::new(input),
-            items,
-        };
-
-        if return_clause.distinct {
-            let columns = return_clause
-                .items
-                .iter()
-                .enumerate()
-                .map(|(i, item)| {
-                    item.alias
-                        .clone()
-                        .unwrap_or_else(|| self.expr_to_name(&item.expr, i))
-                })
-                .collect();
-            Ok(PlanNode::Distinct {
-                input: Box::new(plan),
-                columns,
-            })
-        } else {
-            Ok(plan)
-        }
+        self.plan_projection(&return_clause.items, return_clause.distinct, input)
     }
 
     fn plan_order_by(&self, order_clause: &OrderByClause, input: PlanNode) -> Result<PlanNode> {
@@ -496,8 +563,22 @@ impl QueryPlanner {
     }
 
     fn plan_with(&self, with_clause: &WithClause, input: PlanNode) -> Result<PlanNode> {
-        let items: Vec<(Expr, String)> = with_clause
-            .items
+        self.plan_projection(&with_clause.items, with_clause.distinct, input)
+    }
+
+    /// Plan RETURN/WITH items: an optional Aggregate, then Project and Distinct.
+    ///
+    /// When any item contains an aggregate call, the items without one become
+    /// the grouping keys. Each aggregate call is computed by the Aggregate and
+    /// read back by alias, so `count(*) + 1` projects over the count. A WHERE
+    /// following the clause is then planned above the Aggregate.
+    fn plan_projection(
+        &self,
+        return_items: &[ReturnItem],
+        distinct: bool,
+        input: PlanNode,
+    ) -> Result<PlanNode> {
+        let items: Vec<(Expr, String)> = return_items
             .iter()
             .enumerate()
             .map(|(i, item)| {
@@ -509,22 +590,57 @@ impl QueryPlanner {
             })
             .collect();
 
+        let mut has_aggregate = Vec::with_capacity(items.len());
+        for (expr, _) in &items {
+            has_aggregate.push(Self::contains_aggregate(expr)?);
+        }
+
+        let (input, items) = if has_aggregate.contains(&true) {
+            let group_by: Vec<Expr> = items
+                .iter()
+                .zip(&has_aggregate)
+                .filter(|(_, aggregated)| !**aggregated)
+                .map(|((expr, _), _)| expr.clone())
+                .collect();
+            let mut aggregates = Vec::new();
+            let mut projected = Vec::with_capacity(items.len());
+
+            for (expr, alias) in items {
+                if let Some((op, arg, distinct)) = Self::aggregate_call(&expr)? {
+                    Self::reject_nested_aggregate(&arg)?;
+                    aggregates.push((op, arg, alias.clone(), distinct));
+                    projected.push((Expr::Variable(alias.clone()), alias));
+                    continue;
+                }
+
+                let expr = Self::lift_aggregates(expr, &mut aggregates)?;
+                let mut bound: Vec<String> =
+                    aggregates.iter().map(|(_, _, name, _)| name.clone()).collect();
+                if !Self::is_grouped(&expr, &group_by, &mut bound) {
+                    return Err(QueryError::PlanningError(format!(
+                        "'{alias}' mixes aggregates with expressions that are not grouping keys"
+                    )));
+                }
+                projected.push((expr, alias));
+            }
+
+            let aggregate = PlanNode::Aggregate {
+                input: Box::new(input),
+                group_by,
+                aggregates,
+            };
+            (aggregate, projected)
+        } else {
+            (input, items)
+        };
+
+        let columns: Vec<String> = items.iter().map(|(_, alias)| alias.clone()).collect();
         let plan = PlanNode::Project {
             input: Box::new(input),
             items,
         };
 
-        if with_clause.distinct {
-            let columns = with_clause
-                .items
-                .iter()
-                .enumerate()
-                .map(|(i, item)| {
-                    item.alias
-                        .clone()
-                        .unwrap_or_else(|| self.expr_to_name(&item.expr, i))
-                })
-                .collect();
+        if distinct {
             Ok(PlanNode::Distinct {
                 input: Box::new(plan),
                 columns,
@@ -534,11 +650,190 @@ impl QueryPlanner {
         }
     }
 
+    /// Recognize an aggregate function call, returning its op, argument and
+    /// DISTINCT flag.
+    ///
+    /// `count(*)` is planned as counting a non-null constant, i.e. every row.
+    fn aggregate_call(expr: &Expr) -> Result<Option<(AggregateOp, Expr, bool)>> {
+        let Expr::FunctionCall {
+            name,
+            args,
+            distinct,
+        } = expr
+        else {
+            return Ok(None);
+        };
+
+        let op = match name.to_lowercase().as_str() {
+            "count" => AggregateOp::Count,
+            "sum" => AggregateOp::Sum,
+            "avg" => AggregateOp::Avg,
+            "min" => AggregateOp::Min,
+            "max" => AggregateOp::Max,
+            "collect" => AggregateOp::Collect,
+            _ => return Ok(None),
+        };
+
+        match args.as_slice() {
+            [] if op == AggregateOp::Count => {
+                Ok(Some((op, Expr::Literal(Literal::Boolean(true)), false)))
+            }
+            [arg] => Ok(Some((op, arg.clone(), *distinct))),
+            _ => Err(QueryError::PlanningError(format!(
+                "{name}() takes exactly one argument"
+            ))),
+        }
+    }
+
+    fn contains_aggregate(expr: &Expr) -> Result<bool> {
+        let mut found = Vec::new();
+        Self::lift_aggregates(expr.clone(), &mut found)?;
+        Ok(!found.is_empty())
+    }
+
+    fn reject_nested_aggregate(arg: &Expr) -> Result<()> {
+        if Self::contains_aggregate(arg)? {
+            return Err(QueryError::PlanningError(
+                "aggregate calls cannot be nested".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Replace each aggregate call inside `expr` with a variable naming its
+    /// column, adding the call to `aggregates`.
+    ///
+    /// Columns are named by the call text, so a repeated call is computed once.
+    fn lift_aggregates(
+        expr: Expr,
+        aggregates: &mut Vec<(AggregateOp, Expr, String, bool)>,
+    ) -> Result<Expr> {
+        let Some((op, arg, distinct)) = Self::aggregate_call(&expr)? else {
+            return Self::map_children(expr, &mut |child| Self::lift_aggregates(child, aggregates));
+        };
+
+        Self::reject_nested_aggregate(&arg)?;
+        let name = expr.to_cypher();
+        if !aggregates.iter().any(|(_, _, existing, _)| *existing == name) {
+            aggregates.push((op, arg, name.clone(), distinct));
+        }
+        Ok(Expr::Variable(name))
+    }
+
+    /// Whether `expr`, evaluated above an Aggregate, only reads grouping keys
+    /// and the `bound` names: aggregate columns and comprehension variables.
+    fn is_grouped(expr: &Expr, group_by: &[Expr], bound: &mut Vec<String>) -> bool {
+        if group_by.contains(expr) {
+            return true;
+        }
+        match expr {
+            Expr::Literal(_) | Expr::Parameter(_) => true,
+            Expr::Variable(name) => bound.contains(name),
+            Expr::ListComprehension {
+                variable,
+                list,
+                filter,
+                projection,
+            } => {
+                if !Self::is_grouped(list, group_by, bound) {
+                    return false;
+                }
+                bound.push(variable.clone());
+                let grouped = filter
+                    .iter()
+                    .chain(std::iter::once(projection))
+                    .all(|body| Self::is_grouped(body, group_by, bound));
+                bound.pop();
+                grouped
+            }
+            // Pattern subqueries read the graph rather than the row
+            Expr::PatternComprehension { .. } | Expr::Exists { .. } | Expr::Count { .. } => false,
+            _ => {
+                let mut grouped = true;
+                let _ = Self::map_children(expr.clone(), &mut |child| {
+                    grouped &= Self::is_grouped(&child, group_by, bound);
+                    Ok(child)
+                });
+                grouped
+            }
+        }
+    }
+
+    /// Rebuild `expr` with `f` applied to each direct subexpression.
+    ///
+    /// Pattern subqueries are left as they are.
+    fn map_children(expr: Expr, f: &mut impl FnMut(Expr) -> Result<Expr>) -> Result<Expr> {
+        Ok(match expr {
+            Expr::Property { expr, name } => Expr::Property {
+                expr: Box::new(f(*expr)?),
+                name,
+            },
+            Expr::Index { expr, index } => Expr::Index {
+                expr: Box::new(f(*expr)?),
+                index: Box::new(f(*index)?),
+            },
+            Expr::Binary { left, op, right } => Expr::Binary {
+                left: Box::new(f(*left)?),
+                op,
+                right: Box::new(f(*right)?),
+            },
+            Expr::Unary { op, expr } => Expr::Unary {
+                op,
+                expr: Box::new(f(*expr)?),
+            },
+            Expr::FunctionCall {
+                name,
+                args,
+                distinct,
+            } => Expr::FunctionCall {
+                name,
+                args: args.into_iter().map(&mut *f).collect::<Result<_>>()?,
+                distinct,
+            },
+            Expr::Case {
+                operand,
+                when_clauses,
+                else_clause,
+            } => Expr::Case {
+                operand: operand.map(|expr| f(*expr).map(Box::new)).transpose()?,
+                when_clauses: when_clauses
+                    .into_iter()
+                    .map(|(when, then)| Ok((f(when)?, f(then)?)))
+                    .collect::<Result<_>>()?,
+                else_clause: else_clause.map(|expr| f(*expr).map(Box::new)).transpose()?,
+            },
+            Expr::List(items) => Expr::List(items.into_iter().map(&mut *f).collect::<Result<_>>()?),
+            Expr::Map(entries) => Expr::Map(
+                entries
+                    .into_iter()
+                    .map(|(key, value)| Ok((key, f(value)?)))
+                    .collect::<Result<_>>()?,
+            ),
+            Expr::ListComprehension {
+                variable,
+                list,
+                filter,
+                projection,
+            } => Expr::ListComprehension {
+                variable,
+                list: Box::new(f(*list)?),
+                filter: filter.map(|expr| f(*expr).map(Box::new)).transpose()?,
+                projection: Box::new(f(*projection)?),
+            },
+            Expr::InSet { expr, set } => Expr::InSet {
+                expr: Box::new(f(*expr)?),
+                set,
+            },
+            leaf => leaf,
+        })
+    }
+
     fn expr_to_name(&self, expr: &Expr, index: usize) -> String {
         match expr {
             Expr::Variable(name) => name.clone(),
             Expr::Property { name, .. } => name.clone(),
-            Expr::FunctionCall { name, .. } => name.clone(),
+            // The full call, so `count(DISTINCT x)` and `count(x)` differ
+            Expr::FunctionCall { .. } => expr.to_cypher(),
             _ => format!("_col{index}"),
         }
     }
@@ -583,4 +878,266 @@ mod tests {
 
         assert!(has_filter(&plan.root));
     }
+
+    #[test]
+    fn test_merge_plan() {
+        let parser = QueryParser::new();
+        let planner = QueryPlanner::new();
+
+        let query = parser
+            .parse("MERGE (n:Person {id: 1}) ON CREATE SET n.visits = 1 ON MATCH SET n.visits = 2")
+            .unwrap();
+        let plan = planner.plan(&query).unwrap();
+
+        match plan.root {
+            PlanNode::Merge {
+                input,
+                pattern,
+                on_create,
+                on_match,
+            } => {
+                assert!(matches!(*input, PlanNode::SingleRow));
+                assert_eq!(pattern.paths.len(), 1);
+                assert_eq!(on_create.len(), 1);
+                assert_eq!(on_match.len(), 1);
+            }
+            other => panic!("expected Merge, got {other:?}"),
+        }
+    }
+
+    fn filter_above_aggregate(node: &PlanNode) -> bool {
+        fn contains_aggregate(node: &PlanNode) -> bool {
+            match node {
+                PlanNode::Aggregate { .. } => true,
+                PlanNode::Project { input, .. }
+                | PlanNode::Filter { input, .. }
+                | PlanNode::Distinct { input, .. } => contains_aggregate(input),
+                _ => false,
+            }
+        }
+
+        match node {
+            PlanNode::Filter { input, .. } => contains_aggregate(input),
+            PlanNode::Project { input, .. } | PlanNode::Distinct { input, .. } => {
+                filter_above_aggregate(input)
+            }
+            _ => false,
+        }
+    }
+
+    #[test]
+    fn test_with_aggregate_then_where() {
+        let parser = QueryParser::new();
+        let planner = QueryPlanner::new();
+
+        let query = parser
+            .parse(
+                "MATCH (p:Person)-[:WORKS_IN]->(d:Dept)
+                 WITH d, count(*) AS c WHERE c > 5
+                 RETURN d.name, c",
+            )
+            .unwrap();
+        let plan = planner.plan(&query).unwrap();
+
+        assert!(filter_above_aggregate(&plan.root));
+    }
+
+    #[test]
+    fn test_aggregate_grouping() {
+        let parser = QueryParser::new();
+        let planner = QueryPlanner::new();
+
+        let query = parser
+            .parse("MATCH (n:Person) RETURN n.city AS city, count(n) AS people, avg(n.age)")
+            .unwrap();
+        let plan = planner.plan(&query).unwrap();
+
+        let PlanNode::Project { input, items } = plan.root else {
+            panic!("expected Project at the root");
+        };
+        let PlanNode::Aggregate {
+            group_by,
+            aggregates,
+            ..
+        } = *input
+        else {
+            panic!("expected Aggregate below Project");
+        };
+
+        assert_eq!(group_by.len(), 1);
+        assert_eq!(aggregates.len(), 2);
+        assert_eq!(aggregates[0].0, AggregateOp::Count);
+        assert_eq!(aggregates[1].0, AggregateOp::Avg);
+        assert_eq!(items[2], (Expr::Variable("avg(n.age)".to_string()), "avg(n.age)".to_string()));
+    }
+
+    #[test]
+    fn test_unaliased_aggregates_named_by_call() {
+        let parser = QueryParser::new();
+        let planner = QueryPlanner::new();
+
+        let query = parser
+            .parse("MATCH (n:Person) RETURN count(DISTINCT n.dept), count(n.dept)")
+            .unwrap();
+        let plan = planner.plan(&query).unwrap();
+
+        let PlanNode::Project { input, items } = plan.root else {
+            panic!("expected Project at the root");
+        };
+        let PlanNode::Aggregate { aggregates, .. } = *input else {
+            panic!("expected Aggregate below Project");
+        };
+
+        let aliases: Vec<&str> = aggregates.iter().map(|(_, _, alias, _)| alias.as_str()).collect();
+        assert_eq!(aliases, ["count(DISTINCT n.dept)", "count(n.dept)"]);
+        let columns: Vec<&str> = items.iter().map(|(_, alias)| alias.as_str()).collect();
+        assert_eq!(columns, aliases);
+    }
+
+    #[test]
+    fn test_nested_aggregates_computed_before_projection() {
+        let parser = QueryParser::new();
+        let planner = QueryPlanner::new();
+
+        let query = parser
+            .parse("MATCH (n:Person) RETURN n.dept, sum(n.x) * 2 AS doubled, count(*) + 1")
+            .unwrap();
+        let plan = planner.plan(&query).unwrap();
+
+        let PlanNode::Project { input, items } = plan.root else {
+            panic!("expected Project at the root");
+        };
+        let PlanNode::Aggregate {
+            group_by,
+            aggregates,
+            ..
+        } = *input
+        else {
+            panic!("expected Aggregate below Project");
+        };
+
+        // Only the plain property is a grouping key
+        assert_eq!(group_by.len(), 1);
+        let columns: Vec<&str> = aggregates.iter().map(|(_, _, alias, _)| alias.as_str()).collect();
+        assert_eq!(columns, ["sum(n.x)", "count(*)"]);
+        assert_eq!(
+            items[1].0,
+            Expr::Binary {
+                left: Box::new(Expr::Variable("sum(n.x)".to_string())),
+                op: BinaryOp::Mul,
+                right: Box::new(Expr::Literal(Literal::Integer(2))),
+            }
+        );
+    }
+
+    #[test]
+    fn test_ungrouped_aggregate_expressions_rejected() {
+        let parser = QueryParser::new();
+        let planner = QueryPlanner::new();
+
+        for query in [
+            "MATCH (n:Person) RETURN n.age + count(*)",
+            "MATCH (n:Person) RETURN sum(count(n))",
+            "MATCH (n:Person) RETURN n.dept, 1 + sum(max(n.x))",
+        ] {
+            let parsed = parser.parse(query).unwrap();
+            assert!(
+                matches!(planner.plan(&parsed), Err(QueryError::PlanningError(_))),
+                "{query}"
+            );
+        }
+
+        // Grouping keys may appear beside aggregates
+        let query = parser
+            .parse("MATCH (n:Person) RETURN n.dept, n.dept + count(*), size(collect(n.x))")
+            .unwrap();
+        assert!(planner.plan(&query).is_ok());
+    }
+
+    #[test]
+    fn test_count_distinct_plan() {
+        let parser = QueryParser::new();
+        let planner = QueryPlanner::new();
+
+        let query = parser
+            .parse(
+                "MATCH (n:Person) RETURN count(DISTINCT n.dept) AS depts, count(n.dept) AS total",
+            )
+            .unwrap();
+        let plan = planner.plan(&query).unwrap();
+
+        let PlanNode::Project { input, .. } = plan.root else {
+            panic!("expected Project at the root");
+        };
+        let PlanNode::Aggregate { aggregates, .. } = *input else {
+            panic!("expected Aggregate below Project");
+        };
+
+        let flags: Vec<(&str, bool)> = aggregates
+            .iter()
+            .map(|(_, _, alias, distinct)| (alias.as_str(), *distinct))
+            .collect();
+        assert_eq!(flags, [("depts", true), ("total", false)]);
+    }
+
+    fn plan_root(query: &str) -> PlanNode {
+        let query = QueryParser::new().parse(query).unwrap();
+        QueryPlanner::new().plan(&query).unwrap().root
+    }
+
+    #[test]
+    fn test_property_map_lifted_to_where() {
+        assert_eq!(
+            plan_root("MATCH (n:Person {age: 30}) RETURN n"),
+            plan_root("MATCH (n:Person) WHERE n.age = 30 RETURN n")
+        );
+
+        // Merged with the MATCH's own WHERE, anonymous elements named
+        let inline =
+            plan_root("MATCH (a {x: 1})-[:KNOWS {since: 2020}]->(b) WHERE b.y > 0 RETURN a");
+        let explicit = plan_root(
+            "MATCH (a)-[`  UNNAMED0`:KNOWS]->(b) \
+             WHERE a.x = 1 AND `  UNNAMED0`.since = 2020 AND b.y > 0 RETURN a",
+        );
+        assert_eq!(format!("{inline:?}"), format!("{explicit:?}"));
+    }
+
+    #[test]
+    fn test_property_map_lift_names_unique_across_paths() {
+        let inline = plan_root("MATCH (a)-[{x: 1}]->(b), (c)-[{y: 2}]->(d) RETURN a");
+        let explicit = plan_root(
+            "MATCH (a)-[`  UNNAMED0`]->(b), (c)-[`  UNNAMED1`]->(d) \
+             WHERE `  UNNAMED0`.x = 1 AND `  UNNAMED1`.y = 2 RETURN a",
+        );
+        assert_eq!(format!("{inline:?}"), format!("{explicit:?}"));
+    }
+
+    #[test]
+    fn test_property_map_lift_names_unique_across_clauses() {
+        let inline = plan_root("MATCH (a)-[{x: 1}]->(b) MATCH (b)-[{y: 2}]->(c) RETURN c");
+        let explicit = plan_root(
+            "MATCH (a)-[`  UNNAMED0`]->(b) WHERE `  UNNAMED0`.x = 1 \
+             MATCH (b)-[`  UNNAMED1`]->(c) WHERE `  UNNAMED1`.y = 2 RETURN c",
+        );
+        assert_eq!(format!("{inline:?}"), format!("{explicit:?}"));
+    }
+
+    #[test]
+    fn test_property_map_kept_on_variable_length_edge() {
+        let query = QueryParser::new()
+            .parse("MATCH (a)-[r:KNOWS*1..3 {since: 2020}]->(b) RETURN b")
+            .unwrap();
+        let lifted = QueryPlanner::new().lift_property_maps(&query, &mut Vec::new());
+
+        assert_eq!(lifted, query);
+    }
+
+    #[test]
+    fn test_property_map_lift_keeps_index_hint() {
+        let query = QueryParser::new().parse("MATCH (n:Person {age: 30}) RETURN n").unwrap();
+        let plan = QueryPlanner::new().plan(&query).unwrap();
+
+        assert_eq!(plan.required_indexes.len(), 1);
+        assert_eq!(plan.required_indexes[0].property, "age");
+    }
 }
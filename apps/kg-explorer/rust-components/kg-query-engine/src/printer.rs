@@ -0,0 +1,650 @@
+//! Query pretty-printer.
+//!
+//! Turns an AST back into canonical query text: uppercase keywords, one
+//! space between clauses, and parentheses only where the parser's
+//! precedence rules require them.
+
+use crate::ast::*;
+use crate::parser::{Lexer, Token};
+use indexmap::IndexMap;
+use std::fmt::Write;
+
+/// Binding strength of each expression level, mirroring the parser's
+/// recursive descent (higher binds tighter).
+mod prec {
+    pub const OR: u8 = 1;
+    pub const XOR: u8 = 2;
+    pub const AND: u8 = 3;
+    pub const NOT: u8 = 4;
+    pub const COMPARISON: u8 = 5;
+    pub const ADDITIVE: u8 = 6;
+    pub const MULTIPLICATIVE: u8 = 7;
+    pub const POWER: u8 = 8;
+    pub const UNARY: u8 = 9;
+    pub const POSTFIX: u8 = 10;
+}
+
+impl Query {
+    /// Serialize the query back into query text.
+    ///
+    /// For any query the parser produced, parsing the output yields an equal
+    /// AST. NaN and `i64::MIN` have no literal form, so they print as
+    /// arithmetic that evaluates to them and reparse as a binary expression.
+    #[must_use]
+    pub fn to_cypher(&self) -> String {
+        let mut printer = Printer::default();
+        for (i, clause) in self.clauses.iter().enumerate() {
+            if i > 0 {
+                printer.out.push(' ');
+            }
+            printer.clause(clause);
+        }
+        printer.out
+    }
+}
+
+impl Expr {
+    /// Serialize the expression into query text.
+    #[must_use]
+    pub fn to_cypher(&self) -> String {
+        let mut printer = Printer::default();
+        printer.expr(self, 0);
+        printer.out
+    }
+}
+
+#[derive(Default)]
+struct Printer {
+    out: String,
+}
+
+impl Printer {
+    fn clause(&mut self, clause: &Clause) {
+        match clause {
+            Clause::Match(m) => {
+                self.out.push_str(if m.optional { "OPTIONAL MATCH " } else { "MATCH " });
+                self.pattern(&m.pattern);
+            }
+            Clause::Where(w) => {
+                self.out.push_str("WHERE ");
+                self.expr(&w.predicate, 0);
+            }
+            Clause::Return(r) => self.projection("RETURN", r.distinct, &r.items),
+            Clause::With(w) => self.projection("WITH", w.distinct, &w.items),
+            Clause::OrderBy(o) => {
+                self.out.push_str("ORDER BY ");
+                self.separated(&o.items, |p, item| {
+                    p.expr(&item.expr, 0);
+                    if !item.ascending {
+                        p.out.push_str(" DESC");
+                    }
+                });
+            }
+            Clause::Limit(l) => {
+                let _ = write!(self.out, "LIMIT {}", l.count);
+            }
+            Clause::Skip(s) => {
+                let _ = write!(self.out, "SKIP {}", s.count);
+            }
+            Clause::Create(c) => {
+                self.out.push_str("CREATE ");
+                self.pattern(&c.pattern);
+            }
+            Clause::Merge(m) => {
+                self.out.push_str("MERGE ");
+                self.pattern(&m.pattern);
+                if !m.on_create.is_empty() {
+                    self.out.push_str(" ON CREATE SET ");
+                    self.set_items(&m.on_create);
+                }
+                if !m.on_match.is_empty() {
+                    self.out.push_str(" ON MATCH SET ");
+                    self.set_items(&m.on_match);
+                }
+            }
+            Clause::Set(s) => {
+                self.out.push_str("SET ");
+                self.set_items(&s.items);
+            }
+            Clause::Delete(d) => {
+                self.out.push_str(if d.detach { "DETACH DELETE " } else { "DELETE " });
+                self.separated(&d.items, |p, item| p.expr(item, 0));
+            }
+            Clause::Unwind(u) => {
+                self.out.push_str("UNWIND ");
+                self.expr(&u.expr, 0);
+                self.out.push_str(" AS ");
+                self.ident(&u.alias);
+            }
+        }
+    }
+
+    fn projection(&mut self, keyword: &str, distinct: bool, items: &[ReturnItem]) {
+        self.out.push_str(keyword);
+        self.out.push_str(if distinct { " DISTINCT " } else { " " });
+        self.separated(items, |p, item| {
+            p.expr(&item.expr, 0);
+            if let Some(alias) = &item.alias {
+                p.out.push_str(" AS ");
+                p.ident(alias);
+            }
+        });
+    }
+
+    fn set_items(&mut self, items: &[SetItem]) {
+        self.separated(items, |p, item| {
+            p.expr(&item.target, prec::POSTFIX);
+            p.out.push_str(" = ");
+            p.expr(&item.value, 0);
+        });
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        self.separated(&pattern.paths, |p, path| {
+            for element in &path.elements {
+                match element {
+                    PathElement::Node(node) => p.node(node),
+                    PathElement::Edge(edge) => p.edge(edge),
+                }
+            }
+        });
+    }
+
+    fn node(&mut self, node: &NodePattern) {
+        self.out.push('(');
+        if let Some(variable) = &node.variable {
+            self.ident(variable);
+        }
+        for label in &node.labels {
+            self.out.push(':');
+            self.ident(label);
+        }
+        if !node.properties.is_empty() {
+            if node.variable.is_some() || !node.labels.is_empty() {
+                self.out.push(' ');
+            }
+            self.map(&node.properties);
+        }
+        self.out.push(')');
+    }
+
+    fn edge(&mut self, edge: &EdgePattern) {
+        self.out.push_str(if edge.direction == Direction::Incoming { "<-" } else { "-" });
+
+        let has_details = edge.variable.is_some()
+            || !edge.rel_types.is_empty()
+            || edge.length.is_some()
+            || !edge.properties.is_empty();
+        if has_details {
+            self.out.push('[');
+            if let Some(variable) = &edge.variable {
+                self.ident(variable);
+            }
+            for (i, rel_type) in edge.rel_types.iter().enumerate() {
+                self.out.push(if i == 0 { ':' } else { '|' });
+                self.ident(rel_type);
+            }
+            if let Some(length) = &edge.length {
+                self.length(length);
+            }
+            if !edge.properties.is_empty() {
+                if !self.out.ends_with('[') {
+                    self.out.push(' ');
+                }
+                self.map(&edge.properties);
+            }
+            self.out.push(']');
+        }
+
+        self.out.push_str(if edge.direction == Direction::Outgoing { "->" } else { "-" });
+    }
+
+    fn length(&mut self, length: &LengthSpec) {
+        self.out.push('*');
+        match (length.min, length.max) {
+            (Some(min), Some(max)) if min == max => self.out.push_str(&min.to_string()),
+            (min, max) if min.is_some() || max.is_some() => {
+                if let Some(min) = min {
+                    self.out.push_str(&min.to_string());
+                }
+                self.out.push_str("..");
+                if let Some(max) = max {
+                    self.out.push_str(&max.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn map(&mut self, map: &IndexMap<String, Expr>) {
+        self.out.push('{');
+        self.separated(map, |p, (key, value)| {
+            p.ident(key);
+            p.out.push_str(": ");
+            p.expr(value, 0);
+        });
+        self.out.push('}');
+    }
+
+    /// Print `expr`, parenthesized if it binds looser than `min_prec`.
+    fn expr(&mut self, expr: &Expr, min_prec: u8) {
+        let parenthesize = Self::precedence(expr) < min_prec;
+        if parenthesize {
+            self.out.push('(');
+        }
+        self.expr_inner(expr);
+        if parenthesize {
+            self.out.push(')');
+        }
+    }
+
+    fn expr_inner(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal(literal) => self.literal(literal),
+            Expr::Variable(name) => self.ident(name),
+            Expr::Parameter(name) => {
+                self.out.push('$');
+                self.out.push_str(name);
+            }
+            Expr::Property { expr, name } => {
+                self.expr(expr, prec::POSTFIX);
+                self.out.push('.');
+                self.ident(name);
+            }
+            Expr::Index { expr, index } => {
+                self.expr(expr, prec::POSTFIX);
+                self.out.push('[');
+                self.expr(index, 0);
+                self.out.push(']');
+            }
+            Expr::Binary { left, op, right } => self.binary(left, *op, right),
+            Expr::Unary { op, expr } => match op {
+                UnaryOp::Not => {
+                    self.out.push_str("NOT ");
+                    self.expr(expr, prec::NOT);
+                }
+                UnaryOp::Neg | UnaryOp::Pos => {
+                    self.out.push(if *op == UnaryOp::Neg { '-' } else { '+' });
+                    self.expr(expr, prec::UNARY);
+                }
+            },
+            Expr::FunctionCall {
+                name,
+                args,
+                distinct,
+            } => self.function_call(name, args, *distinct),
+            Expr::Case {
+                operand,
+                when_clauses,
+                else_clause,
+            } => self.case(operand.as_deref(), when_clauses, else_clause.as_deref()),
+            Expr::List(items) => {
+                self.out.push('[');
+                self.separated(items, |p, item| p.expr(item, 0));
+                self.out.push(']');
+            }
+            Expr::Map(map) => self.map(map),
+            Expr::PatternComprehension {
+                pattern,
+                where_clause,
+                projection,
+            } => {
+                self.out.push('[');
+                self.pattern(pattern);
+                if let Some(predicate) = where_clause {
+                    self.out.push_str(" WHERE ");
+                    self.expr(predicate, 0);
+                }
+                self.out.push_str(" | ");
+                self.expr(projection, 0);
+                self.out.push(']');
+            }
+            Expr::ListComprehension {
+                variable,
+                list,
+                filter,
+                projection,
+            } => {
+                self.out.push('[');
+                self.ident(variable);
+                self.out.push_str(" IN ");
+                self.expr(list, 0);
+                if let Some(predicate) = filter {
+                    self.out.push_str(" WHERE ");
+                    self.expr(predicate, 0);
+                }
+                self.out.push_str(" | ");
+                self.expr(projection, 0);
+                self.out.push(']');
+            }
+            Expr::Exists { pattern } => {
+                self.out.push_str("EXISTS {");
+                self.pattern(pattern);
+                self.out.push('}');
+            }
+            Expr::Count { pattern } => {
+                self.out.push_str("COUNT {");
+                self.pattern(pattern);
+                self.out.push('}');
+            }
+            Expr::InSet { expr, set } => {
+                self.expr(expr, prec::COMPARISON + 1);
+                self.out.push_str(" IN [");
+                self.separated(set.literals(), Self::literal);
+                self.out.push(']');
+            }
+        }
+    }
+
+    fn function_call(&mut self, name: &str, args: &[Expr], distinct: bool) {
+        // COUNT is a keyword; the parser handles `count(...)` itself
+        let is_count = name.eq_ignore_ascii_case("count");
+        if is_count {
+            self.out.push_str(name);
+        } else {
+            self.ident(name);
+        }
+        self.out.push('(');
+        if distinct {
+            self.out.push_str("DISTINCT ");
+        }
+        if is_count && args.is_empty() {
+            self.out.push('*');
+        }
+        self.separated(args, |p, arg| p.expr(arg, 0));
+        self.out.push(')');
+    }
+
+    fn case(
+        &mut self,
+        operand: Option<&Expr>,
+        when_clauses: &[(Expr, Expr)],
+        else_clause: Option<&Expr>,
+    ) {
+        self.out.push_str("CASE");
+        if let Some(operand) = operand {
+            self.out.push(' ');
+            self.expr(operand, 0);
+        }
+        for (when, then) in when_clauses {
+            self.out.push_str(" WHEN ");
+            self.expr(when, 0);
+            self.out.push_str(" THEN ");
+            self.expr(then, 0);
+        }
+        if let Some(else_clause) = else_clause {
+            self.out.push_str(" ELSE ");
+            self.expr(else_clause, 0);
+        }
+        self.out.push_str(" END");
+    }
+
+    fn binary(&mut self, left: &Expr, op: BinaryOp, right: &Expr) {
+        let p = Self::binary_precedence(op);
+        let (symbol, left_prec, right_prec) = match op {
+            BinaryOp::Or => ("OR", p, p + 1),
+            BinaryOp::Xor => ("XOR", p, p + 1),
+            BinaryOp::And => ("AND", p, p + 1),
+            BinaryOp::Add => ("+", p, p + 1),
+            BinaryOp::Sub => ("-", p, p + 1),
+            BinaryOp::Mul => ("*", p, p + 1),
+            BinaryOp::Div => ("/", p, p + 1),
+            BinaryOp::Mod => ("%", p, p + 1),
+            // Right-associative
+            BinaryOp::Pow => ("^", p + 1, p),
+            BinaryOp::IsNull | BinaryOp::IsNotNull => {
+                self.expr(left, p + 1);
+                self.out.push_str(if op == BinaryOp::IsNull {
+                    " IS NULL"
+                } else {
+                    " IS NOT NULL"
+                });
+                return;
+            }
+            // Comparisons do not chain
+            BinaryOp::Eq => ("=", p + 1, p + 1),
+            BinaryOp::Ne => ("<>", p + 1, p + 1),
+            BinaryOp::Lt => ("<", p + 1, p + 1),
+            BinaryOp::Le => ("<=", p + 1, p + 1),
+            BinaryOp::Gt => (">", p + 1, p + 1),
+            BinaryOp::Ge => (">=", p + 1, p + 1),
+            BinaryOp::In => ("IN", p + 1, p + 1),
+            BinaryOp::Contains => ("CONTAINS", p + 1, p + 1),
+            BinaryOp::StartsWith => ("STARTS WITH", p + 1, p + 1),
+            BinaryOp::EndsWith => ("ENDS WITH", p + 1, p + 1),
+            BinaryOp::Matches => ("=~", p + 1, p + 1),
+        };
+
+        self.expr(left, left_prec);
+        self.out.push(' ');
+        self.out.push_str(symbol);
+        self.out.push(' ');
+        self.expr(right, right_prec);
+    }
+
+    fn literal(&mut self, literal: &Literal) {
+        match literal {
+            Literal::Null => self.out.push_str("NULL"),
+            Literal::Boolean(b) => self.out.push_str(if *b { "TRUE" } else { "FALSE" }),
+            // The magnitude of i64::MIN does not fit an integer literal
+            Literal::Integer(i64::MIN) => self.out.push_str("-9223372036854775807 - 1"),
+            Literal::Integer(n) => self.out.push_str(&n.to_string()),
+            // There is no NaN literal; the division folds back to NaN
+            Literal::Float(f) if f.is_nan() => self.out.push_str("0.0 / 0.0"),
+            // Overflows to infinity when parsed
+            Literal::Float(f) if f.is_infinite() => {
+                self.out.push_str(if *f > 0.0 { "1e999" } else { "-1e999" });
+            }
+            // Debug formatting always keeps a decimal point or exponent
+            Literal::Float(f) => {
+                let _ = write!(self.out, "{f:?}");
+            }
+            Literal::String(s) => {
+                let quote = if has_bare(s, '\'') && !has_bare(s, '"') { '"' } else { '\'' };
+                self.quoted(s, quote);
+            }
+        }
+    }
+
+    /// Write `text` between `quote`s.
+    ///
+    /// Parsed strings and names keep their source escapes, so those pass
+    /// through as is; only bare `quote`s and a dangling backslash are escaped.
+    fn quoted(&mut self, text: &str, quote: char) {
+        self.out.push(quote);
+        let mut escaped = false;
+        for c in text.chars() {
+            if c == quote && !escaped {
+                self.out.push('\\');
+            }
+            escaped = c == '\\' && !escaped;
+            self.out.push(c);
+        }
+        if escaped {
+            self.out.push('\\');
+        }
+        self.out.push(quote);
+    }
+
+    /// Print a name, backtick-quoted if it is not a plain identifier.
+    fn ident(&mut self, name: &str) {
+        let plain = name
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_alphabetic() || c == '_')
+            && name.chars().all(|c| c.is_alphanumeric() || c == '_')
+            && matches!(Lexer::keyword_or_ident(name), Token::Ident(_));
+
+        if plain {
+            self.out.push_str(name);
+        } else {
+            self.quoted(name, '`');
+        }
+    }
+
+    fn separated<I, T>(&mut self, items: I, mut write: impl FnMut(&mut Self, T))
+    where
+        I: IntoIterator<Item = T>,
+    {
+        for (i, item) in items.into_iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+            write(self, item);
+        }
+    }
+
+    fn precedence(expr: &Expr) -> u8 {
+        match expr {
+            Expr::Binary { op, .. } => Self::binary_precedence(*op),
+            Expr::Unary { op: UnaryOp::Not, .. } => prec::NOT,
+            Expr::Unary { .. } => prec::UNARY,
+            Expr::InSet { .. } => prec::COMPARISON,
+            Expr::Literal(Literal::Float(f)) if f.is_nan() => prec::MULTIPLICATIVE,
+            Expr::Literal(Literal::Integer(i64::MIN)) => prec::ADDITIVE,
+            // Printed with a leading minus
+            Expr::Literal(Literal::Integer(n)) if *n < 0 => prec::UNARY,
+            Expr::Literal(Literal::Float(f)) if f.is_sign_negative() => prec::UNARY,
+            _ => prec::POSTFIX,
+        }
+    }
+
+    const fn binary_precedence(op: BinaryOp) -> u8 {
+        match op {
+            BinaryOp::Or => prec::OR,
+            BinaryOp::Xor => prec::XOR,
+            BinaryOp::And => prec::AND,
+            BinaryOp::Add | BinaryOp::Sub => prec::ADDITIVE,
+            BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod => prec::MULTIPLICATIVE,
+            BinaryOp::Pow => prec::POWER,
+            BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge
+            | BinaryOp::In
+            | BinaryOp::Contains
+            | BinaryOp::StartsWith
+            | BinaryOp::EndsWith
+            | BinaryOp::Matches
+            | BinaryOp::IsNull
+            | BinaryOp::IsNotNull => prec::COMPARISON,
+        }
+    }
+}
+
+/// Whether `text` contains `quote` without a preceding escape.
+fn has_bare(text: &str, quote: char) -> bool {
+    let mut escaped = false;
+    for c in text.chars() {
+        if c == quote && !escaped {
+            return true;
+        }
+        escaped = c == '\\' && !escaped;
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ast::*;
+    use crate::parser::QueryParser;
+
+    fn assert_round_trip(query: &str) {
+        let parser = QueryParser::new();
+        let first = parser.parse(query).unwrap();
+        let text = first.to_cypher();
+        let second = parser
+            .parse(&text)
+            .unwrap_or_else(|e| panic!("failed to re-parse {text:?}: {e}"));
+        assert_eq!(first, second, "round trip through {text:?}");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for query in [
+            "MATCH (n:Person) RETURN n",
+            "MATCH (a:Person {name: 'Alice', age: 30})-[r:KNOWS|FOLLOWS*2..4]->(b) RETURN a, b",
+            "OPTIONAL MATCH (a)<-[:LIKES]-(b), (c)--(d) RETURN DISTINCT a.name AS name",
+            "MATCH (n) WHERE n.age > 21 AND NOT n.banned OR n.admin XOR n.vip RETURN n",
+            "MATCH (n) WHERE (n.a OR n.b) AND n.c IS NOT NULL RETURN n",
+            "MATCH (n) RETURN (n.x + 2) * 3, 2 ^ 3 ^ 2, (2 ^ 3) ^ 2, n.tags[0]",
+            "MATCH (n {t: -5}) RETURN n.a - (n.b - 1), -n.c ^ 2, -(n.d ^ 2), -1.5e3",
+            "MATCH (n) WITH n, count(*) AS c WHERE c > 5 RETURN n.id, count(DISTINCT n.city)",
+            "MATCH (n) WHERE n.id IN [1, 2.5, $ids] AND exists {(n)-->()} RETURN {k: [n]}",
+            "MATCH (n) RETURN n ORDER BY n.name DESC, n.age SKIP 10 LIMIT 5",
+            "MERGE (p:Person {id: 1}) ON CREATE SET p.created = 1 ON MATCH SET p.seen = p.seen + 1",
+            "MATCH (`first name`:`Odd Label`) RETURN `first name`.`key`, \"it's\"",
+            "MATCH (a)-[*]->(b)-[*..3]-(c)<-[e*2..]-(d) RETURN e",
+        ] {
+            assert_round_trip(query);
+        }
+    }
+
+    #[test]
+    fn test_canonical_output() {
+        let parser = QueryParser::new();
+        let query = parser
+            .parse("match (n:Person)-[:KNOWS]->(m) where ((n.age > 1)) return n.name as name")
+            .unwrap();
+
+        assert_eq!(
+            query.to_cypher(),
+            "MATCH (n:Person)-[:KNOWS]->(m) WHERE n.age > 1 RETURN n.name AS name"
+        );
+    }
+
+    #[test]
+    fn test_round_trip_escapes() {
+        for query in [
+            r#"RETURN 'say "hi" it\'s'"#,
+            r#"RETURN "it's \"quoted\"", 'back\\slash'"#,
+            r"MATCH (`a\`b`) RETURN `a\`b`.`c\\`",
+            "RETURN 1e999, -1e999, [1e999]",
+        ] {
+            assert_round_trip(query);
+        }
+    }
+
+    /// Query returning the single expression `expr`.
+    fn returning(expr: Expr) -> Query {
+        let mut query = QueryParser::new().parse("RETURN 0").unwrap();
+        let Clause::Return(ret) = &mut query.clauses[0] else {
+            panic!("expected RETURN clause");
+        };
+        ret.items[0].expr = expr;
+        query
+    }
+
+    fn reparses(query: &Query) -> String {
+        let text = query.to_cypher();
+        QueryParser::new()
+            .parse(&text)
+            .unwrap_or_else(|e| panic!("failed to re-parse {text:?}: {e}"));
+        text
+    }
+
+    #[test]
+    fn test_unparseable_values_are_escaped() {
+        let string = returning(Expr::Literal(Literal::String(r#"a'b"c\"#.to_string())));
+        assert_eq!(reparses(&string), r#"RETURN 'a\'b"c\\'"#);
+
+        let name = returning(Expr::Variable("a`b".to_string()));
+        assert_eq!(reparses(&name), r"RETURN `a\`b`");
+
+        let nan = returning(Expr::Binary {
+            left: Box::new(Expr::Literal(Literal::Float(f64::NAN))),
+            op: BinaryOp::Pow,
+            right: Box::new(Expr::Literal(Literal::Integer(2))),
+        });
+        assert_eq!(reparses(&nan), "RETURN (0.0 / 0.0) ^ 2");
+
+        let min = returning(Expr::Binary {
+            left: Box::new(Expr::Literal(Literal::Integer(i64::MIN))),
+            op: BinaryOp::Mul,
+            right: Box::new(Expr::Literal(Literal::Integer(2))),
+        });
+        assert_eq!(reparses(&min), "RETURN (-9223372036854775807 - 1) * 2");
+    }
+}
@@ -0,0 +1,449 @@
+//! Static type checking of query expressions.
+//!
+//! Infers expression types ahead of execution so obvious mistakes, such as
+//! adding a number to a string property, are reported at plan time instead
+//! of failing row by row. Property types come from an optional `Schema`;
+//! without one, property accesses are `Unknown` and never flagged.
+
+use crate::ast::{
+    BinaryOp, Clause, Expr, Literal, Pattern, PathElement, Query, ReturnItem, SetItem, UnaryOp,
+};
+use crate::{QueryError, Result};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Statically inferred type of an expression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ExprType {
+    Int,
+    Float,
+    String,
+    Bool,
+    Node,
+    /// Not known until execution (nulls, parameters, unknown properties)
+    Unknown,
+}
+
+impl ExprType {
+    fn is_numeric(self) -> bool {
+        matches!(self, Self::Int | Self::Float)
+    }
+}
+
+impl fmt::Display for ExprType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Int => "Int",
+            Self::Float => "Float",
+            Self::String => "String",
+            Self::Bool => "Bool",
+            Self::Node => "Node",
+            Self::Unknown => "Unknown",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Known property types per node label.
+#[derive(Debug, Clone, Default)]
+pub struct Schema {
+    properties: HashMap<String, HashMap<String, ExprType>>,
+}
+
+impl Schema {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record that `property` on nodes labelled `label` has type `ty`.
+    #[must_use]
+    pub fn with_property(mut self, label: &str, property: &str, ty: ExprType) -> Self {
+        self.properties
+            .entry(label.to_string())
+            .or_default()
+            .insert(property.to_string(), ty);
+        self
+    }
+
+    /// Type of `property` on a node carrying `labels`, if any label knows it.
+    #[must_use]
+    pub fn property_type(&self, labels: &[String], property: &str) -> ExprType {
+        labels
+            .iter()
+            .filter_map(|label| self.properties.get(label)?.get(property))
+            .copied()
+            .next()
+            .unwrap_or(ExprType::Unknown)
+    }
+}
+
+/// Walks a query, inferring expression types and rejecting ill-typed
+/// operations.
+#[derive(Debug, Default)]
+pub struct TypeChecker<'a> {
+    schema: Option<&'a Schema>,
+    scope: Scope,
+}
+
+/// Variables bound at some point of a query.
+#[derive(Debug, Default)]
+struct Scope {
+    /// Labels of node variables
+    nodes: HashMap<String, Vec<String>>,
+    /// Types of other variables, bound by WITH, RETURN and UNWIND
+    values: HashMap<String, ExprType>,
+}
+
+impl<'a> TypeChecker<'a> {
+    #[must_use]
+    pub fn new(schema: Option<&'a Schema>) -> Self {
+        Self {
+            schema,
+            scope: Scope::default(),
+        }
+    }
+
+    /// Check every expression in `query`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QueryError::TypeError` for the first operation whose operand
+    /// types are known to be incompatible.
+    pub fn check(mut self, query: &Query) -> Result<()> {
+        for clause in &query.clauses {
+            self.check_clause(clause)?;
+        }
+        Ok(())
+    }
+
+    fn check_clause(&mut self, clause: &Clause) -> Result<()> {
+        match clause {
+            Clause::Match(m) => self.bind_pattern(&m.pattern),
+            Clause::Create(c) => self.bind_pattern(&c.pattern),
+            Clause::Merge(m) => {
+                self.bind_pattern(&m.pattern)?;
+                self.check_set_items(m.on_create.iter().chain(&m.on_match))
+            }
+            Clause::Where(w) => self.infer(&w.predicate).map(drop),
+            Clause::Return(r) => {
+                // ORDER BY sees the returned aliases next to earlier variables
+                let projected = self.project(&r.items)?;
+                for name in projected.values.keys() {
+                    self.scope.nodes.remove(name);
+                }
+                self.scope.nodes.extend(projected.nodes);
+                self.scope.values.extend(projected.values);
+                Ok(())
+            }
+            Clause::With(w) => {
+                // Only the projected aliases stay in scope
+                self.scope = self.project(&w.items)?;
+                Ok(())
+            }
+            Clause::OrderBy(o) => {
+                o.items.iter().try_for_each(|item| self.infer(&item.expr).map(drop))
+            }
+            Clause::Set(s) => self.check_set_items(&s.items),
+            Clause::Delete(d) => d.items.iter().try_for_each(|expr| self.infer(expr).map(drop)),
+            Clause::Unwind(u) => {
+                self.infer(&u.expr)?;
+                self.scope.nodes.remove(&u.alias);
+                self.scope.values.insert(u.alias.clone(), ExprType::Unknown);
+                Ok(())
+            }
+            Clause::Limit(_) | Clause::Skip(_) => Ok(()),
+        }
+    }
+
+    /// Check projection items and type the variables they bind.
+    ///
+    /// A projected node variable keeps its labels; anything else binds the
+    /// inferred type of its expression. Unaliased expressions other than
+    /// variables bind nothing.
+    fn project(&self, items: &[ReturnItem]) -> Result<Scope> {
+        let mut projected = Scope::default();
+        for item in items {
+            let ty = self.infer(&item.expr)?;
+            let name = match (&item.alias, &item.expr) {
+                (Some(alias), _) => alias,
+                (None, Expr::Variable(var)) => var,
+                (None, _) => continue,
+            };
+            match &item.expr {
+                Expr::Variable(var) if ty == ExprType::Node => {
+                    projected.nodes.insert(name.clone(), self.scope.nodes[var].clone());
+                }
+                _ => {
+                    projected.values.insert(name.clone(), ty);
+                }
+            }
+        }
+        Ok(projected)
+    }
+
+    fn check_set_items<'i>(&self, items: impl IntoIterator<Item = &'i SetItem>) -> Result<()> {
+        for item in items {
+            self.infer(&item.target)?;
+            self.infer(&item.value)?;
+        }
+        Ok(())
+    }
+
+    fn bind_pattern(&mut self, pattern: &Pattern) -> Result<()> {
+        for element in pattern.paths.iter().flat_map(|path| &path.elements) {
+            let properties = match element {
+                PathElement::Node(node) => {
+                    if let Some(var) = &node.variable {
+                        let labels = self.scope.nodes.entry(var.clone()).or_default();
+                        labels.extend(node.labels.iter().cloned());
+                    }
+                    &node.properties
+                }
+                PathElement::Edge(edge) => &edge.properties,
+            };
+            for value in properties.values() {
+                self.infer(value)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Infer the type of `expr`, checking its subexpressions.
+    ///
+    /// # Errors
+    ///
+    /// Returns `QueryError::TypeError` if `expr` or any subexpression is
+    /// ill-typed.
+    pub fn infer(&self, expr: &Expr) -> Result<ExprType> {
+        match expr {
+            Expr::Literal(literal) => Ok(match literal {
+                Literal::Integer(_) => ExprType::Int,
+                Literal::Float(_) => ExprType::Float,
+                Literal::String(_) => ExprType::String,
+                Literal::Boolean(_) => ExprType::Bool,
+                Literal::Null => ExprType::Unknown,
+            }),
+            Expr::Variable(name) => Ok(if self.scope.nodes.contains_key(name) {
+                ExprType::Node
+            } else {
+                self.scope.values.get(name).copied().unwrap_or(ExprType::Unknown)
+            }),
+            Expr::Property { expr, name } => {
+                self.infer(expr)?;
+                Ok(self.property_type(expr, name))
+            }
+            Expr::Binary { left, op, right } => {
+                let left = self.infer(left)?;
+                let right = self.infer(right)?;
+                Self::binary(*op, left, right)
+            }
+            Expr::Unary { op, expr } => {
+                let ty = self.infer(expr)?;
+                match op {
+                    UnaryOp::Not => Self::expect(ExprType::Bool, ty).map(|()| ExprType::Bool),
+                    UnaryOp::Neg | UnaryOp::Pos => Self::expect_numeric(ty).map(|()| ty),
+                }
+            }
+            Expr::Index { expr, index } => {
+                self.infer(expr)?;
+                self.infer(index)?;
+                Ok(ExprType::Unknown)
+            }
+            Expr::FunctionCall { args, .. } => {
+                args.iter().try_for_each(|arg| self.infer(arg).map(drop))?;
+                Ok(ExprType::Unknown)
+            }
+            Expr::Case {
+                operand,
+                when_clauses,
+                else_clause,
+            } => {
+                let nested = operand.iter().chain(else_clause).map(AsRef::as_ref);
+                let arms = when_clauses.iter().flat_map(|(when, then)| [when, then]);
+                nested.chain(arms).try_for_each(|e| self.infer(e).map(drop))?;
+                Ok(ExprType::Unknown)
+            }
+            Expr::List(items) => {
+                items.iter().try_for_each(|item| self.infer(item).map(drop))?;
+                Ok(ExprType::Unknown)
+            }
+            Expr::Map(entries) => {
+                entries.values().try_for_each(|value| self.infer(value).map(drop))?;
+                Ok(ExprType::Unknown)
+            }
+            Expr::ListComprehension { list, .. } => {
+                // The filter and projection see a variable this checker
+                // does not track, so only the source list is checked
+                self.infer(list)?;
+                Ok(ExprType::Unknown)
+            }
+            Expr::InSet { expr, .. } => {
+                self.infer(expr)?;
+                Ok(ExprType::Bool)
+            }
+            Expr::Exists { .. } => Ok(ExprType::Bool),
+            Expr::Count { .. } => Ok(ExprType::Int),
+            Expr::Parameter(_) | Expr::PatternComprehension { .. } => Ok(ExprType::Unknown),
+        }
+    }
+
+    fn property_type(&self, expr: &Expr, name: &str) -> ExprType {
+        let (Some(schema), Expr::Variable(var)) = (self.schema, expr) else {
+            return ExprType::Unknown;
+        };
+        self.scope.nodes
+            .get(var)
+            .map_or(ExprType::Unknown, |labels| schema.property_type(labels, name))
+    }
+
+    fn binary(op: BinaryOp, left: ExprType, right: ExprType) -> Result<ExprType> {
+        use ExprType::{Bool, Float, Int, String, Unknown};
+
+        match op {
+            BinaryOp::Add => match (left, right) {
+                (Int, Int) => Ok(Int),
+                (l, r) if l.is_numeric() && r.is_numeric() => Ok(Float),
+                (String, String) => Ok(String),
+                // A string on one side makes this a concatenation
+                (String, other) | (other, String) => {
+                    Self::expect(String, other).map(|()| String)
+                }
+                (l, r) => {
+                    Self::expect_numeric(l)?;
+                    Self::expect_numeric(r)?;
+                    Ok(Unknown)
+                }
+            },
+            BinaryOp::Sub | BinaryOp::Mul | BinaryOp::Div | BinaryOp::Mod | BinaryOp::Pow => {
+                Self::expect_numeric(left)?;
+                Self::expect_numeric(right)?;
+                Ok(match (left, right) {
+                    (Int, Int) if op != BinaryOp::Pow => Int,
+                    (l, r) if l.is_numeric() && r.is_numeric() => Float,
+                    _ => Unknown,
+                })
+            }
+            BinaryOp::And | BinaryOp::Or | BinaryOp::Xor => {
+                Self::expect(Bool, left)?;
+                Self::expect(Bool, right)?;
+                Ok(Bool)
+            }
+            BinaryOp::Contains | BinaryOp::StartsWith | BinaryOp::EndsWith | BinaryOp::Matches => {
+                Self::expect(String, left)?;
+                Self::expect(String, right)?;
+                Ok(Bool)
+            }
+            BinaryOp::Eq
+            | BinaryOp::Ne
+            | BinaryOp::Lt
+            | BinaryOp::Le
+            | BinaryOp::Gt
+            | BinaryOp::Ge
+            | BinaryOp::In
+            | BinaryOp::IsNull
+            | BinaryOp::IsNotNull => Ok(Bool),
+        }
+    }
+
+    fn expect(expected: ExprType, found: ExprType) -> Result<()> {
+        if found == expected || found == ExprType::Unknown {
+            Ok(())
+        } else {
+            Err(Self::mismatch(&expected.to_string(), found))
+        }
+    }
+
+    fn expect_numeric(found: ExprType) -> Result<()> {
+        if found.is_numeric() || found == ExprType::Unknown {
+            Ok(())
+        } else {
+            Err(Self::mismatch("Int or Float", found))
+        }
+    }
+
+    fn mismatch(expected: &str, found: ExprType) -> QueryError {
+        QueryError::TypeError {
+            expected: expected.to_string(),
+            found: found.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ast::UnwindClause;
+    use crate::parser::QueryParser;
+
+    fn check(query: &str, schema: Option<&Schema>) -> Result<()> {
+        let ast = QueryParser::new().parse(query).unwrap();
+        TypeChecker::new(schema).check(&ast)
+    }
+
+    fn person_schema() -> Schema {
+        Schema::new()
+            .with_property("Person", "name", ExprType::String)
+            .with_property("Person", "age", ExprType::Int)
+    }
+
+    #[test]
+    fn test_string_property_plus_int_is_rejected() {
+        let schema = person_schema();
+        let err = check("MATCH (n:Person) RETURN n.name + 5", Some(&schema)).unwrap_err();
+
+        assert!(matches!(
+            err,
+            QueryError::TypeError { ref expected, ref found }
+                if expected == "String" && found == "Int"
+        ));
+    }
+
+    #[test]
+    fn test_unknown_types_are_not_flagged() {
+        // Without schema the property type is unknown
+        assert!(check("MATCH (n:Person) RETURN n.name + 5", None).is_ok());
+
+        let schema = person_schema();
+        assert!(check("MATCH (n:Person) RETURN n.nickname + 5", Some(&schema)).is_ok());
+        assert!(check("MATCH (n) RETURN n.name + 5", Some(&schema)).is_ok());
+        assert!(check("MATCH (n:Person) RETURN n.age + 5, n.name + '!'", Some(&schema)).is_ok());
+    }
+
+    #[test]
+    fn test_literal_mismatches() {
+        assert!(check("MATCH (n) WHERE n.x > 1 AND 5 RETURN n", None).is_err());
+        assert!(check("MATCH (n) RETURN -'a'", None).is_err());
+        assert!(check("MATCH (n) RETURN n - 1", None).is_err());
+        assert!(check("MATCH (n) RETURN 1 + 2.5, 'a' + 'b', 2 * 3", None).is_ok());
+    }
+
+    #[test]
+    fn test_with_and_unwind_rebind_variables() {
+        assert!(check("MATCH (n) WITH n.name AS n RETURN n + '!'", None).is_ok());
+        assert!(check("MATCH (n) WITH count(n) AS n RETURN n + 1", None).is_ok());
+        assert!(check("MATCH (n) WITH n.x AS x RETURN n + 1", None).is_ok());
+
+        // Projected types are carried forward
+        assert!(check("MATCH (n) WITH n, 'a' AS s RETURN s - 1", None).is_err());
+        assert!(check("MATCH (n) WITH n AS m RETURN m - 1", None).is_err());
+
+        let schema = person_schema();
+        let err = check("MATCH (n:Person) WITH n AS m RETURN m.name + 5", Some(&schema));
+        assert!(err.is_err());
+    }
+
+    #[test]
+    fn test_unwind_rebinds_variable() {
+        // The parser has no UNWIND yet, so splice the clause in
+        let mut ast = QueryParser::new().parse("MATCH (n) RETURN n + 1").unwrap();
+        ast.clauses.insert(
+            1,
+            Clause::Unwind(UnwindClause {
+                expr: Expr::List(vec![Expr::Literal(Literal::Integer(1))]),
+                alias: "n".to_string(),
+            }),
+        );
+        assert!(TypeChecker::new(None).check(&ast).is_ok());
+    }
+}
@@ -0,0 +1,128 @@
+//! Pluggable time source for backend waits.
+//!
+//! Draining polls a buffer with sleeps in between. Routing those through a
+//! `Clock` lets tests swap in `MockClock`, which advances virtual time
+//! instead of blocking the thread.
+
+use std::time::{Duration, Instant};
+use parking_lot::Mutex;
+
+/// Source of time and sleeping for backend polling loops.
+pub trait Clock: Send + Sync {
+    /// Current instant.
+    fn now(&self) -> Instant;
+
+    /// Block (or pretend to block) for `duration`.
+    fn sleep(&self, duration: Duration);
+}
+
+/// Wall-clock time backed by `std`.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        std::thread::sleep(duration);
+    }
+}
+
+/// Manually driven clock for deterministic tests.
+///
+/// `sleep` returns immediately after advancing virtual time.
+pub struct MockClock {
+    origin: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl MockClock {
+    /// Create a mock clock starting at zero elapsed time.
+    pub fn new() -> Self {
+        Self {
+            origin: Instant::now(),
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Move virtual time forward.
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock() += duration;
+    }
+
+    /// Total virtual time elapsed since creation.
+    pub fn elapsed(&self) -> Duration {
+        *self.elapsed.lock()
+    }
+}
+
+impl Default for MockClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> Instant {
+        self.origin + self.elapsed()
+    }
+
+    fn sleep(&self, duration: Duration) {
+        self.advance(duration);
+    }
+}
+
+/// Poll `is_done` every `interval` until it returns true or `timeout` elapses.
+///
+/// Returns `false` on timeout.
+pub fn poll_until(
+    clock: &dyn Clock,
+    timeout: Duration,
+    interval: Duration,
+    mut is_done: impl FnMut() -> bool,
+) -> bool {
+    let start = clock.now();
+    while !is_done() {
+        if clock.now().duration_since(start) > timeout {
+            return false;
+        }
+        clock.sleep(interval);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_clock_advance() {
+        let clock = MockClock::new();
+        let start = clock.now();
+
+        clock.advance(Duration::from_millis(30));
+        clock.sleep(Duration::from_millis(20));
+
+        assert_eq!(clock.now() - start, Duration::from_millis(50));
+        assert_eq!(clock.elapsed(), Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_poll_until_times_out_in_virtual_time() {
+        let clock = MockClock::new();
+        let wall = Instant::now();
+
+        let done = poll_until(
+            &clock,
+            Duration::from_secs(5),
+            Duration::from_millis(10),
+            || false,
+        );
+
+        assert!(!done);
+        assert!(clock.elapsed() > Duration::from_secs(5));
+        assert!(wall.elapsed() < Duration::from_secs(1));
+    }
+}
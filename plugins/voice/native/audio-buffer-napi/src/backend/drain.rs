@@ -0,0 +1,165 @@
+//! Waiting for playback streams to play out their queued audio.
+
+use std::time::Duration;
+use parking_lot::Mutex;
+
+use crate::backend::clock::{self, Clock};
+use crate::backend::{Backend, Result, StreamHandle};
+
+/// How often a draining stream is checked.
+pub const DRAIN_POLL: Duration = Duration::from_millis(10);
+
+/// Default limit on how long a drain may take.
+pub const DRAIN_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Run `step` every [`DRAIN_POLL`] until it reports the stream drained or
+/// `timeout` elapses.
+///
+/// Returns `Ok(false)` on timeout. An error from `step` ends the wait.
+pub fn wait(
+    clock: &dyn Clock,
+    timeout: Duration,
+    mut step: impl FnMut() -> Result<bool>,
+) -> Result<bool> {
+    let mut error = None;
+    let drained = clock::poll_until(clock, timeout, DRAIN_POLL, || match step() {
+        Ok(done) => done,
+        Err(e) => {
+            error = Some(e);
+            true
+        }
+    });
+    match error {
+        Some(e) => Err(e),
+        None => Ok(drained),
+    }
+}
+
+/// Wait up to `timeout` for a playback stream on a shared backend to play
+/// out all queued audio, returning whether it finished.
+///
+/// The backend is locked only for each step, so other calls can use it
+/// while the drain is waiting. Waits on the backend's clock.
+pub fn drain(
+    backend: &Mutex<Box<dyn Backend>>,
+    handle: StreamHandle,
+    timeout: Duration,
+) -> Result<bool> {
+    let clock = backend.lock().clock();
+    let drained = wait(clock.as_ref(), timeout, || backend.lock().drain_step(handle))?;
+    if !drained {
+        backend.lock().end_drain(handle)?;
+    }
+    Ok(drained)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+    use std::sync::mpsc::{self, Receiver, SyncSender};
+    use std::thread;
+    use std::time::Instant;
+
+    use crate::backend::clock::MockClock;
+    use crate::backend::mock::MockBackend;
+    use crate::backend::{BackendError, StreamConfig, StreamState};
+
+    /// Clock that hands control to the test on every sleep.
+    struct SteppedClock {
+        inner: MockClock,
+        /// Sends `true` on each sleep
+        sleeping: SyncSender<bool>,
+        resume: parking_lot::Mutex<Receiver<()>>,
+    }
+
+    impl Clock for SteppedClock {
+        fn now(&self) -> Instant {
+            self.inner.now()
+        }
+
+        fn sleep(&self, duration: Duration) {
+            self.sleeping.send(true).unwrap();
+            self.resume.lock().recv().unwrap();
+            self.inner.sleep(duration);
+        }
+    }
+
+    fn backend_with_audio(clock: Arc<dyn Clock>) -> (Arc<Mutex<Box<dyn Backend>>>, StreamHandle) {
+        let mut backend = MockBackend::with_clock(clock);
+        backend.initialize().unwrap();
+        let handle = backend.create_stream(StreamConfig::default()).unwrap();
+        let samples = vec![0.5f32; StreamConfig::default().prebuffer_samples()];
+        backend.write(handle, &samples).unwrap();
+        backend.start(handle).unwrap();
+        (Arc::new(Mutex::new(Box::new(backend))), handle)
+    }
+
+    #[test]
+    fn test_backend_usable_during_drain() {
+        let (sleeping_tx, sleeping_rx) = mpsc::sync_channel(0);
+        let (resume_tx, resume_rx) = mpsc::sync_channel(0);
+        let (backend, handle) = backend_with_audio(Arc::new(SteppedClock {
+            inner: MockClock::new(),
+            sleeping: sleeping_tx.clone(),
+            resume: parking_lot::Mutex::new(resume_rx),
+        }));
+
+        let draining = {
+            let backend = backend.clone();
+            thread::spawn(move || {
+                let result = drain(&backend, handle, DRAIN_TIMEOUT);
+                sleeping_tx.send(false).unwrap();
+                result
+            })
+        };
+
+        // Each sleep is a point where the drain is in progress
+        let mut fills = Vec::new();
+        while sleeping_rx.recv().unwrap() {
+            let health = backend.try_lock().expect("lock held during drain").get_health(handle);
+            fills.push(health.unwrap().fill_level);
+            resume_tx.send(()).unwrap();
+        }
+
+        assert!(draining.join().unwrap().unwrap());
+        assert!(fills.len() > 1);
+        assert!(fills.windows(2).all(|w| w[1] < w[0]));
+        assert_eq!(backend.lock().get_health(handle).unwrap().fill_level, 0.0);
+
+        // Drained streams can be started again
+        assert_eq!(backend.lock().get_state(handle).unwrap(), StreamState::Idle);
+        backend.lock().start(handle).unwrap();
+    }
+
+    #[test]
+    fn test_drain_reports_timeout() {
+        let (backend, handle) = backend_with_audio(Arc::new(MockClock::new()));
+
+        let drained = drain(&backend, handle, Duration::from_millis(20)).unwrap();
+
+        assert!(!drained);
+        assert!(backend.lock().get_health(handle).unwrap().fill_level > 0.0);
+        assert_eq!(backend.lock().get_state(handle).unwrap(), StreamState::Running);
+    }
+
+    #[test]
+    fn test_drain_leaves_paused_stream_alone() {
+        let (backend, handle) = backend_with_audio(Arc::new(MockClock::new()));
+        backend.lock().pause(handle).unwrap();
+
+        // Nothing plays out of a paused stream
+        let drained = drain(&backend, handle, Duration::from_millis(20)).unwrap();
+
+        assert!(!drained);
+        assert_eq!(backend.lock().get_state(handle).unwrap(), StreamState::Paused);
+    }
+
+    #[test]
+    fn test_drain_unknown_stream() {
+        let (backend, _) = backend_with_audio(Arc::new(MockClock::new()));
+        let result = drain(&backend, StreamHandle::new(999), DRAIN_TIMEOUT);
+
+        assert!(matches!(result, Err(BackendError::StreamNotFound(_))));
+    }
+}
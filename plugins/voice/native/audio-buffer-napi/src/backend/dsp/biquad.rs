@@ -0,0 +1,159 @@
+//! Second-order IIR filter for simple equalization.
+//!
+//! Coefficients follow the RBJ audio EQ cookbook.
+
+use std::f32::consts::PI;
+
+/// Filter response shape.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterKind {
+    Lowpass,
+    Highpass,
+    /// Band-pass with 0 dB gain at the center frequency
+    Bandpass,
+}
+
+/// Biquad filter settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BiquadConfig {
+    pub kind: FilterKind,
+    /// Cutoff (or center) frequency in Hz
+    pub cutoff_hz: f32,
+    /// Quality factor; 0.707 gives a maximally flat low/high-pass
+    pub q: f32,
+}
+
+/// Biquad filter over interleaved samples.
+///
+/// Keeps separate state per channel, so a stream can be processed in
+/// arbitrary chunks without discontinuities at buffer boundaries.
+pub struct Biquad {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+    a1: f32,
+    a2: f32,
+    /// Transposed direct form II delay line per channel
+    state: Vec<[f32; 2]>,
+}
+
+impl Biquad {
+    /// Create a filter for an interleaved stream with the given format.
+    ///
+    /// The cutoff is clamped below Nyquist and Q to a small positive value.
+    pub fn new(config: BiquadConfig, sample_rate: u32, channels: u32) -> Self {
+        let nyquist = sample_rate as f32 / 2.0;
+        let cutoff = config.cutoff_hz.clamp(1.0, nyquist * 0.99);
+        let q = config.q.max(0.01);
+
+        let w0 = 2.0 * PI * cutoff / sample_rate as f32;
+        let cos_w0 = w0.cos();
+        let alpha = w0.sin() / (2.0 * q);
+
+        let (b0, b1, b2) = match config.kind {
+            FilterKind::Lowpass => {
+                let b = (1.0 - cos_w0) / 2.0;
+                (b, 1.0 - cos_w0, b)
+            }
+            FilterKind::Highpass => {
+                let b = (1.0 + cos_w0) / 2.0;
+                (b, -(1.0 + cos_w0), b)
+            }
+            FilterKind::Bandpass => (alpha, 0.0, -alpha),
+        };
+        let a0 = 1.0 + alpha;
+
+        Self {
+            b0: b0 / a0,
+            b1: b1 / a0,
+            b2: b2 / a0,
+            a1: -2.0 * cos_w0 / a0,
+            a2: (1.0 - alpha) / a0,
+            state: vec![[0.0; 2]; channels.max(1) as usize],
+        }
+    }
+
+    /// Filter interleaved samples in place.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        let channels = self.state.len();
+
+        for frame in samples.chunks_mut(channels) {
+            for (sample, z) in frame.iter_mut().zip(self.state.iter_mut()) {
+                let x = *sample;
+                let y = self.b0 * x + z[0];
+                z[0] = self.b1 * x - self.a1 * y + z[1];
+                z[1] = self.b2 * x - self.a2 * y;
+                *sample = y;
+            }
+        }
+    }
+
+    /// Clear filter history, e.g. after a seek or restart.
+    pub fn reset(&mut self) {
+        self.state.iter_mut().for_each(|z| *z = [0.0; 2]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(freq: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| (i as f32 * 2.0 * PI * freq / 48000.0).sin())
+            .collect()
+    }
+
+    /// RMS of the second half, after the filter has settled.
+    fn settled_rms(samples: &[f32]) -> f32 {
+        let tail = &samples[samples.len() / 2..];
+        (tail.iter().map(|s| s * s).sum::<f32>() / tail.len() as f32).sqrt()
+    }
+
+    fn low_pass() -> Biquad {
+        let config = BiquadConfig {
+            kind: FilterKind::Lowpass,
+            cutoff_hz: 1000.0,
+            q: 0.707,
+        };
+        Biquad::new(config, 48000, 1)
+    }
+
+    #[test]
+    fn test_low_pass_attenuates_high_frequency() {
+        let mut filter = low_pass();
+        let mut samples = sine(12000.0, 9600);
+        let input_rms = settled_rms(&samples);
+
+        filter.process(&mut samples);
+
+        assert!(settled_rms(&samples) < input_rms * 0.01);
+    }
+
+    #[test]
+    fn test_low_pass_passes_low_frequency() {
+        let mut filter = low_pass();
+        let mut samples = sine(100.0, 9600);
+        let input_rms = settled_rms(&samples);
+
+        filter.process(&mut samples);
+
+        assert!(settled_rms(&samples) > input_rms * 0.95);
+    }
+
+    #[test]
+    fn test_state_carries_across_buffers() {
+        let input = sine(440.0, 1000);
+
+        let mut whole = input.clone();
+        low_pass().process(&mut whole);
+
+        let mut chunked = input;
+        let mut filter = low_pass();
+        let (first, second) = chunked.split_at_mut(333);
+        filter.process(first);
+        filter.process(second);
+
+        assert_eq!(whole, chunked);
+    }
+}
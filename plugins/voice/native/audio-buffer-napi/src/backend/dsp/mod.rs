@@ -0,0 +1,14 @@
+//! Per-stream signal processing.
+//!
+//! This module provides:
+//! - Noise gate for recording streams
+//! - Biquad EQ filter for playback streams
+//! - Sample rate conversion for playback streams
+
+pub mod gate;
+pub mod biquad;
+pub mod resample;
+
+pub use gate::{NoiseGate, NoiseGateConfig};
+pub use biquad::{Biquad, BiquadConfig, FilterKind};
+pub use resample::{ResampleQuality, Resampler};
@@ -5,13 +5,18 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
 use parking_lot::Mutex;
 
 use crate::backend::{
-    AudioDevice, Backend, BackendError, Result, StreamConfig, StreamDirection,
-    StreamHandle, StreamState,
+    check_stream_limit, AudioDevice, Backend, BackendError, Capabilities, Result, StreamConfig,
+    StreamDirection, StreamHandle, StreamState, DEFAULT_MAX_STREAMS,
 };
-use crate::buffer::{HealthMetrics, HealthMonitor, RingBuffer};
+use crate::backend::clock::{Clock, SystemClock};
+use crate::backend::drain::DRAIN_POLL;
+use crate::backend::dsp::{Biquad, NoiseGate, Resampler};
+use crate::buffer::{BackpressureListener, HealthMetrics, HealthMonitor, RingBuffer};
+use crate::mix::{RecorderSlot, RecorderTap};
 
 /// Internal stream state for mock backend.
 struct MockStream {
@@ -19,25 +24,86 @@ struct MockStream {
     buffer: RingBuffer,
     health: HealthMonitor,
     volume: f32,
-    state: StreamState,
+    /// Frames consumed since start
+    position: AtomicU64,
+    gate: Option<Mutex<NoiseGate>>,
+    filter: Option<Mutex<Biquad>>,
+    resampler: Option<Mutex<Resampler>>,
+    /// Capture point for the mix-down recorder, fed by `consume`
+    #[cfg_attr(not(test), allow(dead_code))]
+    tap: RecorderTap,
+    /// Tees receiving a copy of every write
+    tees: Vec<StreamHandle>,
 }
 
 impl MockStream {
-    fn new(config: StreamConfig) -> Self {
+    /// Duration of audio the stream buffer is sized for.
+    fn buffer_ms(config: &StreamConfig) -> u32 {
+        config.buffer_size_ms + config.effective_prebuffer_ms()
+    }
+
+    /// Capacity in samples of the buffer `new` makes for `config`.
+    fn capacity(config: &StreamConfig) -> usize {
+        let duration_ms = Self::buffer_ms(config);
+        RingBuffer::capacity_for_duration(config.sample_rate, config.channels, duration_ms)
+    }
+
+    fn new(config: StreamConfig, tap: RecorderTap) -> Self {
         let buffer = RingBuffer::for_duration(
             config.sample_rate,
             config.channels,
-            config.buffer_size_ms + config.prebuffer_ms,
+            Self::buffer_ms(&config),
         );
 
+        let health = HealthMonitor::new();
+        health.set_latency(config.latency_ms());
+        let gate = config.build_noise_gate().map(Mutex::new);
+        let filter = config.build_filter().map(Mutex::new);
+        let resampler = config.build_resampler().map(Mutex::new);
+
         Self {
             config,
             buffer,
-            health: HealthMonitor::new(),
+            health,
             volume: 1.0,
-            state: StreamState::Idle,
+            position: AtomicU64::new(0),
+            gate,
+            filter,
+            resampler,
+            tap,
+            tees: Vec::new(),
         }
     }
+
+    fn state(&self) -> StreamState {
+        self.health.get_state()
+    }
+
+    fn set_state(&self, state: StreamState) {
+        self.health.set_state(state);
+    }
+
+    /// Advance the position by `samples` interleaved samples.
+    fn advance_position(&self, samples: usize) {
+        let frames = samples / self.config.channels.max(1) as usize;
+        self.position.fetch_add(frames as u64, Ordering::Relaxed);
+    }
+
+    /// Publish the buffer's fill level and report any backpressure crossing.
+    fn update_fill_level(&self) {
+        self.health.set_fill_level(self.buffer.fill_percent());
+        self.health.deliver_backpressure();
+    }
+
+    /// Re-check the prebuffer water marks after the fill level changed.
+    fn update_water_marks(&self) {
+        let capacity = self.buffer.capacity();
+        self.health.apply_water_marks(
+            self.buffer.available_read(),
+            self.config.low_water_samples(capacity),
+            self.config.high_water_samples(capacity),
+        );
+    }
 }
 
 /// Mock backend for testing.
@@ -45,15 +111,35 @@ pub struct MockBackend {
     streams: HashMap<StreamHandle, MockStream>,
     next_handle: u32,
     initialized: bool,
+    clock: Arc<dyn Clock>,
+    recorder: RecorderSlot,
+    max_streams: usize,
 }
 
 impl MockBackend {
+    /// Software-only: no device I/O, volume is scaled in software and the
+    /// device lists are fixed placeholders.
+    pub const CAPABILITIES: Capabilities = Capabilities {
+        hardware_io: false,
+        hardware_volume: false,
+        device_enumeration: false,
+        recording: true,
+    };
+
     /// Create a new mock backend.
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Create a mock backend that waits on the given clock.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
         Self {
             streams: HashMap::new(),
             next_handle: 1,
             initialized: false,
+            clock,
+            recorder: RecorderSlot::new(),
+            max_streams: DEFAULT_MAX_STREAMS,
         }
     }
 
@@ -68,6 +154,99 @@ impl MockBackend {
             .get_mut(&handle)
             .ok_or(BackendError::StreamNotFound(handle))
     }
+
+    /// Simulate the audio device pulling up to `count` samples from a playback stream.
+    ///
+    /// Like a real device callback, nothing is consumed unless the stream is
+    /// running or draining; the cycle is still recorded as silence. Returns
+    /// the number of samples consumed.
+    #[cfg(test)]
+    pub fn consume(&self, handle: StreamHandle, count: usize) -> Result<usize> {
+        let stream = self.get_stream(handle)?;
+
+        if !matches!(stream.state(), StreamState::Running | StreamState::Draining) {
+            stream.tap.capture(&vec![0.0; count]);
+            return Ok(0);
+        }
+
+        let mut output = vec![0.0f32; count];
+        let read = stream.buffer.read(&mut output);
+        stream.advance_position(read);
+        stream.update_fill_level();
+
+        if read < count {
+            stream.health.record_underrun();
+            stream.health.apply_underrun(stream.config.underrun_policy);
+        }
+        stream.update_water_marks();
+
+        // What the device would have played, padded with silence
+        for sample in &mut output[..read] {
+            *sample *= stream.volume;
+        }
+        stream.tap.capture(&output);
+
+        Ok(read)
+    }
+
+    /// Simulate microphone input by pushing samples into a recording stream.
+    ///
+    /// Returns the number of samples that fit in the buffer.
+    #[cfg(test)]
+    pub fn feed(&self, handle: StreamHandle, samples: &[f32]) -> Result<usize> {
+        let stream = self.get_stream(handle)?;
+
+        if stream.config.direction != StreamDirection::Recording {
+            return Err(BackendError::InvalidConfig(
+                "Cannot feed a playback stream".into(),
+            ));
+        }
+
+        let written = stream.buffer.write(samples);
+        if written < samples.len() {
+            stream.health.record_overrun();
+        }
+        stream.update_fill_level();
+        stream.update_water_marks();
+
+        Ok(written)
+    }
+
+    /// Copy samples just buffered by a playback stream into its tees.
+    fn mirror(&self, tees: &[StreamHandle], samples: &[f32]) {
+        for tee in tees.iter().filter_map(|handle| self.streams.get(handle)) {
+            let written = tee.buffer.write(samples);
+            if written < samples.len() {
+                tee.health.record_overrun();
+            }
+            tee.update_fill_level();
+        }
+    }
+
+    /// Feed `count` samples of deterministic white noise in [-1.0, 1.0).
+    ///
+    /// The same `seed` always produces the same samples, so tests can
+    /// regenerate the expected signal.
+    #[cfg(test)]
+    pub fn feed_noise(&self, handle: StreamHandle, seed: u32, count: usize) -> Result<usize> {
+        self.feed(handle, &noise(seed, count))
+    }
+}
+
+/// Deterministic white noise from a xorshift32 generator.
+#[cfg(test)]
+fn noise(seed: u32, count: usize) -> Vec<f32> {
+    // Zero is a fixed point of xorshift
+    let mut state = seed.max(1);
+    (0..count)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            // Top 24 bits map exactly onto [0.0, 1.0)
+            (state >> 8) as f32 / (1u32 << 24) as f32 * 2.0 - 1.0
+        })
+        .collect()
 }
 
 impl Default for MockBackend {
@@ -85,6 +264,10 @@ impl Backend for MockBackend {
         true // Mock is always available
     }
 
+    fn capabilities(&self) -> Capabilities {
+        Self::CAPABILITIES
+    }
+
     fn initialize(&mut self) -> Result<()> {
         self.initialized = true;
         Ok(())
@@ -110,11 +293,14 @@ impl Backend for MockBackend {
         if config.channels == 0 || config.channels > 8 {
             return Err(BackendError::InvalidConfig("Channels must be 1-8".into()));
         }
+        config.validate_water_marks(MockStream::capacity(&config))?;
+        check_stream_limit(self.streams.len(), self.max_streams)?;
 
         let handle = StreamHandle::new(self.next_handle);
         self.next_handle += 1;
 
-        let stream = MockStream::new(config);
+        let tap = self.recorder.tap(handle, &config);
+        let stream = MockStream::new(config, tap);
         self.streams.insert(handle, stream);
 
         Ok(handle)
@@ -124,68 +310,95 @@ impl Backend for MockBackend {
         self.streams
             .remove(&handle)
             .ok_or(BackendError::StreamNotFound(handle))?;
+        for stream in self.streams.values_mut() {
+            stream.tees.retain(|&tee| tee != handle);
+        }
         Ok(())
     }
 
+    fn tee(&mut self, source: StreamHandle) -> Result<StreamHandle> {
+        let config = self.get_stream(source)?.config.tee_config()?;
+        let handle = self.create_stream(config)?;
+        self.get_stream(handle)?.set_state(StreamState::Running);
+        self.get_stream_mut(source)?.tees.push(handle);
+        Ok(handle)
+    }
+
+    fn stream_handles(&self) -> Vec<StreamHandle> {
+        self.streams.keys().copied().collect()
+    }
+
+    fn set_max_streams(&mut self, max: usize) {
+        self.max_streams = max;
+    }
+
     fn get_state(&self, handle: StreamHandle) -> Result<StreamState> {
-        Ok(self.get_stream(handle)?.state)
+        Ok(self.get_stream(handle)?.state())
     }
 
     fn start(&mut self, handle: StreamHandle) -> Result<()> {
         let stream = self.get_stream_mut(handle)?;
-        match stream.state {
+        match stream.state() {
             StreamState::Idle | StreamState::Paused => {
+                stream.position.store(0, Ordering::Relaxed);
                 // Check prebuffer requirement
-                let prebuffer_samples = stream.config.prebuffer_samples();
-                if stream.buffer.available_read() >= prebuffer_samples {
-                    stream.state = StreamState::Running;
-                    stream.health.set_state(StreamState::Running);
+                let high_water = stream.config.high_water_samples(stream.buffer.capacity());
+                if stream.buffer.available_read() >= high_water {
+                    stream.set_state(StreamState::Running);
                 } else {
-                    stream.state = StreamState::Prebuffering;
-                    stream.health.set_state(StreamState::Prebuffering);
+                    stream.set_state(StreamState::Prebuffering);
                 }
                 Ok(())
             }
-            _ => Err(BackendError::InvalidState {
+            actual => Err(BackendError::InvalidState {
                 expected: StreamState::Idle,
-                actual: stream.state,
+                actual,
             }),
         }
     }
 
     fn stop(&mut self, handle: StreamHandle) -> Result<()> {
         let stream = self.get_stream_mut(handle)?;
-        stream.state = StreamState::Stopped;
-        stream.health.set_state(StreamState::Stopped);
+        stream.set_state(StreamState::Stopped);
         stream.buffer.clear();
+        stream.buffer.reset_high_water();
+        stream.position.store(0, Ordering::Relaxed);
+        // A restarted stream should not open on a gate closed by old audio
+        if let Some(gate) = &stream.gate {
+            gate.lock().reset();
+        }
+        // Nor ring out the tail of what was cleared from the buffer
+        if let Some(filter) = &stream.filter {
+            filter.lock().reset();
+        }
         Ok(())
     }
 
     fn pause(&mut self, handle: StreamHandle) -> Result<()> {
         let stream = self.get_stream_mut(handle)?;
-        if stream.state == StreamState::Running {
-            stream.state = StreamState::Paused;
-            stream.health.set_state(StreamState::Paused);
-            Ok(())
-        } else {
-            Err(BackendError::InvalidState {
+        match stream.state() {
+            StreamState::Running => {
+                stream.set_state(StreamState::Paused);
+                Ok(())
+            }
+            actual => Err(BackendError::InvalidState {
                 expected: StreamState::Running,
-                actual: stream.state,
-            })
+                actual,
+            }),
         }
     }
 
     fn resume(&mut self, handle: StreamHandle) -> Result<()> {
         let stream = self.get_stream_mut(handle)?;
-        if stream.state == StreamState::Paused {
-            stream.state = StreamState::Running;
-            stream.health.set_state(StreamState::Running);
-            Ok(())
-        } else {
-            Err(BackendError::InvalidState {
+        match stream.state() {
+            StreamState::Paused => {
+                stream.set_state(StreamState::Running);
+                Ok(())
+            }
+            actual => Err(BackendError::InvalidState {
                 expected: StreamState::Paused,
-                actual: stream.state,
-            })
+                actual,
+            }),
         }
     }
 
@@ -198,16 +411,47 @@ impl Backend for MockBackend {
             ));
         }
 
-        let written = stream.buffer.write(samples);
+        let resampled;
+        let (samples, consumed) = match &stream.resampler {
+            Some(resampler) => {
+                let mut resampler = resampler.lock();
+                // All or nothing, so the resampler state never runs ahead
+                // of the buffered audio
+                if resampler.output_len(samples.len()) > stream.buffer.available_write() {
+                    stream.health.record_overrun();
+                    return Ok(0);
+                }
+                resampled = resampler.process(samples);
+                (&resampled[..], Some(samples.len()))
+            }
+            None => (samples, None),
+        };
+
+        let filtered;
+        let buffered = match &stream.filter {
+            Some(filter) => {
+                // Only filter what fits so the filter state tracks the buffered audio
+                let fits = samples.len().min(stream.buffer.available_write());
+                let mut block = samples[..fits].to_vec();
+                filter.lock().process(&mut block);
+                filtered = block;
+                &filtered[..]
+            }
+            None => samples,
+        };
+        let written = stream.buffer.write(buffered);
+        self.mirror(&stream.tees, &buffered[..written]);
 
         // Update health metrics
-        stream.health.set_fill_level(stream.buffer.fill_percent());
+        stream.update_fill_level();
 
         if written < samples.len() {
             stream.health.record_overrun();
         }
+        stream.update_water_marks();
 
-        Ok(written)
+        // Report input samples for resampled writes
+        Ok(consumed.unwrap_or(written))
     }
 
     fn read(&self, handle: StreamHandle, buffer: &mut [f32]) -> Result<usize> {
@@ -220,13 +464,18 @@ impl Backend for MockBackend {
         }
 
         let read = stream.buffer.read(buffer);
+        if let Some(gate) = &stream.gate {
+            gate.lock().process(&mut buffer[..read]);
+        }
+        stream.advance_position(read);
 
         // Update health metrics
-        stream.health.set_fill_level(stream.buffer.fill_percent());
+        stream.update_fill_level();
 
         if read < buffer.len() {
             stream.health.record_underrun();
         }
+        stream.update_water_marks();
 
         Ok(read)
     }
@@ -241,22 +490,67 @@ impl Backend for MockBackend {
         Ok(self.get_stream(handle)?.volume)
     }
 
+    fn get_position(&self, handle: StreamHandle) -> Result<u64> {
+        Ok(self.get_stream(handle)?.position.load(Ordering::Relaxed))
+    }
+
+    fn recorder(&self) -> &RecorderSlot {
+        &self.recorder
+    }
+
     fn get_health(&self, handle: StreamHandle) -> Result<HealthMetrics> {
-        Ok(self.get_stream(handle)?.health.snapshot())
+        let stream = self.get_stream(handle)?;
+        Ok(HealthMetrics {
+            peak_fill_level: stream.buffer.peak_fill_percent(),
+            ..stream.health.snapshot()
+        })
+    }
+
+    fn set_backpressure_listener(
+        &self,
+        handle: StreamHandle,
+        high: f32,
+        low: f32,
+        listener: Option<BackpressureListener>,
+    ) -> Result<()> {
+        self.get_stream(handle)?.health.set_backpressure(high, low, listener)
     }
 
-    fn drain(&self, handle: StreamHandle) -> Result<()> {
+    fn clock(&self) -> Arc<dyn Clock> {
+        Arc::clone(&self.clock)
+    }
+
+    fn drain_step(&self, handle: StreamHandle) -> Result<bool> {
         let stream = self.get_stream(handle)?;
+        if matches!(stream.state(), StreamState::Running | StreamState::Prebuffering) {
+            stream.health.set_state(StreamState::Draining);
+        }
+
+        if stream.buffer.is_empty() {
+            if stream.state() == StreamState::Draining {
+                stream.health.set_state(StreamState::Idle);
+            }
+            return Ok(true);
+        }
+
+        // Simulate the audio callback consuming one poll interval
+        if stream.state() == StreamState::Draining {
+            let tick_samples = (stream.config.sample_rate as usize)
+                * (stream.config.channels as usize)
+                * (DRAIN_POLL.as_millis() as usize)
+                / 1000;
+            stream.buffer.read(&mut vec![0.0f32; tick_samples.max(1)]);
+            stream.update_fill_level();
+        }
+        Ok(false)
+    }
 
-        // In mock, just check that buffer eventually empties
-        // Real implementation would wait for audio callback to consume
-        if stream.buffer.available_read() == 0 {
-            Ok(())
-        } else {
-            // Simulate drain by clearing buffer
-            stream.buffer.clear();
-            Ok(())
+    fn end_drain(&self, handle: StreamHandle) -> Result<()> {
+        let stream = self.get_stream(handle)?;
+        if stream.state() == StreamState::Draining {
+            stream.health.set_state(StreamState::Running);
         }
+        Ok(())
     }
 
     fn list_playback_devices(&self) -> Result<Vec<AudioDevice>> {
@@ -299,6 +593,11 @@ impl Backend for MockBackend {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::backend::clock::MockClock;
+    use crate::backend::drain::{self, DRAIN_TIMEOUT};
+    use crate::backend::dsp::{BiquadConfig, FilterKind, NoiseGateConfig, ResampleQuality};
+    use crate::backend::UnderrunPolicy;
+    use std::time::{Duration, Instant};
 
     #[test]
     fn test_create_and_destroy_stream() {
@@ -316,6 +615,35 @@ mod tests {
         assert!(backend.get_state(handle).is_err());
     }
 
+    #[test]
+    fn test_max_streams_cap() {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+        backend.set_max_streams(2);
+
+        let first = backend.create_stream(StreamConfig::default()).unwrap();
+        backend.create_stream(StreamConfig::default()).unwrap();
+        assert!(matches!(
+            backend.create_stream(StreamConfig::default()),
+            Err(BackendError::InvalidConfig(_))
+        ));
+        assert!(backend.tee(first).is_err());
+
+        // Destroying a stream frees its slot
+        backend.destroy_stream(first).unwrap();
+        backend.create_stream(StreamConfig::default()).unwrap();
+    }
+
+    #[test]
+    fn test_capabilities_are_software_only() {
+        let caps = MockBackend::new().capabilities();
+
+        assert!(!caps.hardware_io);
+        assert!(!caps.hardware_volume);
+        assert!(!caps.device_enumeration);
+        assert!(caps.recording);
+    }
+
     #[test]
     fn test_write_and_read() {
         let mut backend = MockBackend::new();
@@ -342,12 +670,163 @@ mod tests {
             })
             .unwrap();
 
-        // Write to recording buffer (simulating mic input)
-        // In mock, we'd need to push data differently - this tests the error case
+        // Recording streams only take input through `feed`
         let result = backend.write(recording, &samples);
         assert!(result.is_err());
     }
 
+    #[test]
+    fn test_feed_and_read_back() {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let recording = backend
+            .create_stream(StreamConfig {
+                direction: StreamDirection::Recording,
+                ..Default::default()
+            })
+            .unwrap();
+
+        let input: Vec<f32> = (0..480).map(|i| (i as f32 / 480.0) - 0.5).collect();
+        assert_eq!(backend.feed(recording, &input).unwrap(), input.len());
+
+        let mut output = vec![0.0f32; input.len()];
+        assert_eq!(backend.read(recording, &mut output).unwrap(), input.len());
+        assert_eq!(output, input);
+        assert_eq!(backend.get_position(recording).unwrap(), 480);
+
+        // Seeded noise is reproducible
+        backend.feed_noise(recording, 42, 256).unwrap();
+        let mut output = vec![0.0f32; 256];
+        backend.read(recording, &mut output).unwrap();
+        assert_eq!(output, noise(42, 256));
+        assert!(output.iter().all(|s| (-1.0..1.0).contains(s)));
+    }
+
+    #[test]
+    fn test_stop_reopens_noise_gate() {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let recording = backend
+            .create_stream(StreamConfig {
+                direction: StreamDirection::Recording,
+                noise_gate: Some(NoiseGateConfig::default()),
+                ..Default::default()
+            })
+            .unwrap();
+
+        // A second of quiet input closes the gate
+        let quiet = vec![0.01f32; 480];
+        let mut output = vec![0.0f32; quiet.len()];
+        for _ in 0..100 {
+            backend.feed(recording, &quiet).unwrap();
+            backend.read(recording, &mut output).unwrap();
+        }
+        assert!(output[output.len() - 1] < 0.001);
+
+        // After a stop the same input passes through the reopened gate
+        backend.stop(recording).unwrap();
+        backend.feed(recording, &quiet).unwrap();
+        backend.read(recording, &mut output).unwrap();
+        assert!((output[0] - 0.01).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_stop_clears_filter_history() {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let filter = BiquadConfig {
+            kind: FilterKind::Lowpass,
+            cutoff_hz: 1000.0,
+            q: 0.707,
+        };
+        let config = StreamConfig {
+            filter: Some(filter),
+            ..Default::default()
+        };
+        let handle = backend.create_stream(config.clone()).unwrap();
+        backend.write(handle, &[1.0f32; 480]).unwrap();
+
+        // After a stop the filter responds as if freshly built
+        backend.stop(handle).unwrap();
+        backend.write(handle, &[1.0f32; 4]).unwrap();
+        let mut output = [0.0f32; 4];
+        backend.get_stream(handle).unwrap().buffer.read(&mut output);
+
+        let mut expected = [1.0f32; 4];
+        Biquad::new(filter, config.sample_rate, config.channels).process(&mut expected);
+        assert_eq!(output, expected);
+    }
+
+    #[test]
+    fn test_feed_rejects_playback_stream() {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let playback = backend.create_stream(StreamConfig::default()).unwrap();
+        assert!(matches!(
+            backend.feed(playback, &[0.1; 16]),
+            Err(BackendError::InvalidConfig(_))
+        ));
+    }
+
+    #[test]
+    fn test_tee_mirrors_writes() {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let config = StreamConfig {
+            prebuffer_ms: 0,
+            ..Default::default()
+        };
+        let source = backend.create_stream(config).unwrap();
+        let tee = backend.tee(source).unwrap();
+
+        let input: Vec<f32> = (0..240).map(|i| (i as f32 / 240.0) - 0.5).collect();
+        assert_eq!(backend.write(source, &input).unwrap(), input.len());
+
+        let mut mirrored = vec![0.0f32; input.len()];
+        assert_eq!(backend.read(tee, &mut mirrored).unwrap(), input.len());
+        assert_eq!(mirrored, input);
+
+        // Reading the tee leaves the source untouched
+        backend.start(source).unwrap();
+        assert_eq!(backend.consume(source, input.len()).unwrap(), input.len());
+
+        // The tee is read-only and cannot itself be teed
+        assert!(backend.write(tee, &input).is_err());
+        assert!(matches!(backend.tee(tee), Err(BackendError::InvalidConfig(_))));
+
+        // Destroying the tee stops mirroring without affecting the source
+        backend.destroy_stream(tee).unwrap();
+        assert_eq!(backend.write(source, &input).unwrap(), input.len());
+    }
+
+    #[test]
+    fn test_write_resamples_source_rate() {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let handle = backend
+            .create_stream(StreamConfig {
+                source_sample_rate: Some(24000),
+                resample_quality: ResampleQuality::Medium,
+                ..Default::default()
+            })
+            .unwrap();
+
+        // Reports input samples, buffers twice as many at 48kHz
+        assert_eq!(backend.write(handle, &[0.5; 1200]).unwrap(), 1200);
+        let buffered = backend.get_stream(handle).unwrap().buffer.available_read();
+        assert!((2396..=2400).contains(&buffered), "{buffered}");
+
+        // A chunk that would not fit once converted is rejected whole
+        assert_eq!(backend.write(handle, &[0.5; 4800]).unwrap(), 0);
+        assert_eq!(backend.get_stream(handle).unwrap().buffer.available_read(), buffered);
+    }
+
     #[test]
     fn test_volume_control() {
         let mut backend = MockBackend::new();
@@ -380,4 +859,396 @@ mod tests {
         assert_eq!(health.underrun_count, 0);
         assert_eq!(health.overrun_count, 0);
     }
+
+    #[test]
+    fn test_health_reports_peak_fill() {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let handle = backend
+            .create_stream(StreamConfig {
+                prebuffer_ms: 0,
+                ..Default::default()
+            })
+            .unwrap();
+        backend.write(handle, &vec![0.1f32; 2000]).unwrap();
+        backend.start(handle).unwrap();
+        backend.consume(handle, 1500).unwrap();
+
+        let health = backend.get_health(handle).unwrap();
+        assert!(health.peak_fill_level > health.fill_level);
+
+        backend.stop(handle).unwrap();
+        assert_eq!(backend.get_health(handle).unwrap().peak_fill_level, 0.0);
+    }
+
+    #[test]
+    fn test_drain_with_mock_clock() {
+        let clock = Arc::new(MockClock::new());
+        let mut backend = MockBackend::with_clock(clock.clone());
+        backend.initialize().unwrap();
+
+        let handle = backend.create_stream(StreamConfig::default()).unwrap();
+        let backend: Mutex<Box<dyn Backend>> = Mutex::new(Box::new(backend));
+
+        // Empty buffer: returns without advancing time
+        assert!(drain::drain(&backend, handle, DRAIN_TIMEOUT).unwrap());
+        assert_eq!(clock.elapsed(), Duration::ZERO);
+
+        // 50ms of audio drains in 50ms of virtual time, not wall time
+        let samples = vec![0.5f32; StreamConfig::default().prebuffer_samples()];
+        backend.lock().write(handle, &samples).unwrap();
+        backend.lock().start(handle).unwrap();
+
+        let wall = Instant::now();
+        assert!(drain::drain(&backend, handle, DRAIN_TIMEOUT).unwrap());
+
+        assert_eq!(clock.elapsed(), Duration::from_millis(50));
+        assert!(wall.elapsed() < Duration::from_millis(50));
+        assert_eq!(backend.lock().get_health(handle).unwrap().underrun_count, 0);
+    }
+
+    #[test]
+    fn test_latency_offset_reported() {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let plain = backend.create_stream(StreamConfig::default()).unwrap();
+        let base = backend.get_health(plain).unwrap().latency_ms;
+        assert_eq!(base, 70); // 20ms buffer + 50ms prebuffer
+
+        let delayed = backend
+            .create_stream(StreamConfig {
+                latency_offset_ms: 30,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(backend.get_health(delayed).unwrap().latency_ms, base + 30);
+
+        let advanced = backend
+            .create_stream(StreamConfig {
+                latency_offset_ms: -20,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(backend.get_health(advanced).unwrap().latency_ms, base - 20);
+    }
+
+    #[test]
+    fn test_negative_latency_offset_clamps() {
+        let config = StreamConfig {
+            latency_offset_ms: -500,
+            ..Default::default()
+        };
+        assert_eq!(config.latency_ms(), 0);
+        assert_eq!(config.effective_prebuffer_ms(), 0);
+        assert_eq!(config.prebuffer_samples(), 0);
+
+        // Positive offsets pad the playback prebuffer
+        let padded = StreamConfig {
+            latency_offset_ms: 50,
+            ..Default::default()
+        };
+        assert_eq!(padded.prebuffer_samples(), StreamConfig::default().prebuffer_samples() * 2);
+    }
+
+    #[test]
+    fn test_water_mark_transitions() {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let config = StreamConfig {
+            low_water_frac: 0.25,
+            high_water_frac: 0.5,
+            ..Default::default()
+        };
+        let handle = backend.create_stream(config).unwrap();
+        let capacity = backend.get_stream(handle).unwrap().buffer.capacity();
+        let quarter = capacity / 4;
+
+        backend.start(handle).unwrap();
+        assert_eq!(backend.get_state(handle).unwrap(), StreamState::Prebuffering);
+
+        // Filling past high water starts playback
+        backend.write(handle, &vec![0.1f32; quarter]).unwrap();
+        assert_eq!(backend.get_state(handle).unwrap(), StreamState::Prebuffering);
+        backend.write(handle, &vec![0.1f32; quarter]).unwrap();
+        assert_eq!(backend.get_state(handle).unwrap(), StreamState::Running);
+
+        // Dipping below high water keeps running until low water
+        backend.consume(handle, quarter).unwrap();
+        assert_eq!(backend.get_state(handle).unwrap(), StreamState::Running);
+        backend.consume(handle, 1).unwrap();
+        assert_eq!(backend.get_state(handle).unwrap(), StreamState::Prebuffering);
+
+        // While prebuffering the device consumes nothing, so no underruns
+        assert_eq!(backend.consume(handle, quarter).unwrap(), 0);
+        assert_eq!(backend.get_health(handle).unwrap().underrun_count, 0);
+
+        // Refilling past high water resumes
+        backend.write(handle, &vec![0.1f32; quarter + 1]).unwrap();
+        assert_eq!(backend.get_state(handle).unwrap(), StreamState::Running);
+    }
+
+    #[test]
+    fn test_invalid_water_marks_rejected() {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let inverted = StreamConfig {
+            low_water_frac: 0.6,
+            high_water_frac: 0.4,
+            ..Default::default()
+        };
+        assert!(matches!(
+            backend.create_stream(inverted),
+            Err(BackendError::InvalidConfig(_))
+        ));
+
+        let out_of_range = StreamConfig {
+            high_water_frac: 1.5,
+            ..Default::default()
+        };
+        assert!(backend.create_stream(out_of_range).is_err());
+
+        // Above the default prebuffer threshold that stands in for a high mark
+        let above_prebuffer = StreamConfig {
+            low_water_frac: 0.7,
+            ..Default::default()
+        };
+        assert!(matches!(
+            backend.create_stream(above_prebuffer),
+            Err(BackendError::InvalidConfig(_))
+        ));
+    }
+
+    /// Start a stream with `policy`, play through its prebuffer and underrun.
+    fn underrun_with(policy: UnderrunPolicy) -> (MockBackend, StreamHandle) {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let config = StreamConfig {
+            underrun_policy: policy,
+            ..Default::default()
+        };
+        let prebuffer = config.prebuffer_samples();
+        let handle = backend.create_stream(config).unwrap();
+
+        backend.start(handle).unwrap();
+        backend.write(handle, &vec![0.1f32; prebuffer]).unwrap();
+        assert_eq!(backend.get_state(handle).unwrap(), StreamState::Running);
+
+        assert_eq!(backend.consume(handle, prebuffer + 10).unwrap(), prebuffer);
+        assert_eq!(backend.get_health(handle).unwrap().underrun_count, 1);
+        (backend, handle)
+    }
+
+    #[test]
+    fn test_underrun_policy_silence_keeps_running() {
+        let (backend, handle) = underrun_with(UnderrunPolicy::Silence);
+        assert_eq!(backend.get_state(handle).unwrap(), StreamState::Running);
+
+        // The device keeps pulling, so data is played as soon as it arrives
+        backend.write(handle, &[0.1f32; 10]).unwrap();
+        assert_eq!(backend.consume(handle, 10).unwrap(), 10);
+    }
+
+    #[test]
+    fn test_underrun_policy_re_prebuffer_waits_for_high_water() {
+        let (backend, handle) = underrun_with(UnderrunPolicy::RePrebuffer);
+        assert_eq!(backend.get_state(handle).unwrap(), StreamState::Prebuffering);
+
+        let prebuffer = StreamConfig::default().prebuffer_samples();
+        backend.write(handle, &vec![0.1f32; prebuffer - 1]).unwrap();
+        assert_eq!(backend.consume(handle, 10).unwrap(), 0);
+        assert_eq!(backend.get_state(handle).unwrap(), StreamState::Prebuffering);
+
+        backend.write(handle, &[0.1f32]).unwrap();
+        assert_eq!(backend.get_state(handle).unwrap(), StreamState::Running);
+    }
+
+    #[test]
+    fn test_underrun_policy_stop() {
+        let (backend, handle) = underrun_with(UnderrunPolicy::Stop);
+        assert_eq!(backend.get_state(handle).unwrap(), StreamState::Stopped);
+
+        backend.write(handle, &[0.1f32; 10]).unwrap();
+        assert_eq!(backend.consume(handle, 10).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_pause_all_and_resume_all() {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let config = || StreamConfig {
+            prebuffer_ms: 0,
+            ..Default::default()
+        };
+        let running = backend.create_stream(config()).unwrap();
+        let paused = backend.create_stream(config()).unwrap();
+        let idle = backend.create_stream(config()).unwrap();
+
+        backend.start(running).unwrap();
+        backend.start(paused).unwrap();
+        backend.pause(paused).unwrap();
+
+        assert_eq!(backend.pause_all().unwrap(), 1);
+        assert_eq!(backend.get_state(running).unwrap(), StreamState::Paused);
+        assert_eq!(backend.get_state(paused).unwrap(), StreamState::Paused);
+        assert_eq!(backend.get_state(idle).unwrap(), StreamState::Idle);
+
+        assert_eq!(backend.resume_all().unwrap(), 2);
+        assert_eq!(backend.get_state(running).unwrap(), StreamState::Running);
+        assert_eq!(backend.get_state(paused).unwrap(), StreamState::Running);
+        assert_eq!(backend.get_state(idle).unwrap(), StreamState::Idle);
+    }
+
+    #[test]
+    fn test_position_tracks_consumed_frames() {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let handle = backend
+            .create_stream(StreamConfig {
+                channels: 2,
+                prebuffer_ms: 0,
+                ..Default::default()
+            })
+            .unwrap();
+        backend.write(handle, &vec![0.1f32; 2000]).unwrap();
+
+        // Nothing is consumed before start
+        backend.consume(handle, 400).unwrap();
+        assert_eq!(backend.get_position(handle).unwrap(), 0);
+
+        backend.start(handle).unwrap();
+        backend.consume(handle, 400).unwrap();
+        backend.consume(handle, 600).unwrap();
+        assert_eq!(backend.get_position(handle).unwrap(), 500);
+
+        backend.stop(handle).unwrap();
+        assert_eq!(backend.get_position(handle).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_backpressure_watermarks() {
+        use crate::buffer::BackpressureEvent;
+
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let handle = backend
+            .create_stream(StreamConfig {
+                prebuffer_ms: 0,
+                ..Default::default()
+            })
+            .unwrap();
+        let capacity = backend.get_stream(handle).unwrap().buffer.capacity();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&events);
+        backend
+            .set_backpressure_listener(
+                handle,
+                0.75,
+                0.25,
+                Some(Arc::new(move |event| seen.lock().push(event))),
+            )
+            .unwrap();
+
+        // Producer outruns the device
+        backend.write(handle, &vec![0.1f32; capacity / 2]).unwrap();
+        assert!(events.lock().is_empty());
+        backend.write(handle, &vec![0.1f32; capacity * 3 / 10]).unwrap();
+        assert_eq!(*events.lock(), vec![BackpressureEvent::High]);
+
+        // Device drains it below the low watermark
+        backend.start(handle).unwrap();
+        backend.consume(handle, capacity * 6 / 10).unwrap();
+        assert_eq!(
+            *events.lock(),
+            vec![BackpressureEvent::High, BackpressureEvent::Low]
+        );
+    }
+
+    #[test]
+    fn test_recording_captures_mixed_output() {
+        use crate::backend::AudioFormat;
+
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let config = StreamConfig {
+            prebuffer_ms: 0,
+            ..Default::default()
+        };
+        let voice = backend.create_stream(config.clone()).unwrap();
+        let music = backend.create_stream(config).unwrap();
+        backend.set_volume(music, 0.5).unwrap();
+
+        backend.start_recording(48000, 1);
+        backend.write(voice, &[0.25; 480]).unwrap();
+        backend.write(music, &[0.5; 240]).unwrap();
+        backend.start(voice).unwrap();
+        backend.start(music).unwrap();
+        backend.consume(voice, 480).unwrap();
+        backend.consume(music, 480).unwrap();
+
+        let recorder = backend.stop_recording().unwrap();
+        assert_eq!(recorder.frames(), 480);
+
+        let wav = recorder.to_wav(AudioFormat::F32LE);
+        let payload: Vec<f32> = wav[44..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        // Music underruns halfway, leaving only the voice
+        assert!(payload[..240].iter().all(|s| (s - 0.5).abs() < 1e-6));
+        assert!(payload[240..].iter().all(|s| (s - 0.25).abs() < 1e-6));
+        assert!(backend.stop_recording().is_none());
+    }
+
+    #[test]
+    fn test_recording_keeps_late_start_in_place() {
+        use crate::backend::AudioFormat;
+
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+
+        let config = StreamConfig {
+            prebuffer_ms: 0,
+            ..Default::default()
+        };
+        let first = backend.create_stream(config.clone()).unwrap();
+        let late = backend.create_stream(config).unwrap();
+
+        backend.start_recording(48000, 1);
+        backend.write(first, &[0.25; 960]).unwrap();
+        backend.start(first).unwrap();
+
+        // One device cycle with `late` idle, one with both playing
+        backend.consume(first, 480).unwrap();
+        backend.consume(late, 480).unwrap();
+        backend.write(late, &[0.5; 480]).unwrap();
+        backend.start(late).unwrap();
+        backend.consume(first, 480).unwrap();
+        backend.consume(late, 480).unwrap();
+
+        // Pausing holds `late` in place instead of pulling it earlier
+        backend.pause(late).unwrap();
+        backend.consume(late, 480).unwrap();
+
+        let recorder = backend.stop_recording().unwrap();
+        assert_eq!(recorder.frames(), 1440);
+
+        let wav = recorder.to_wav(AudioFormat::F32LE);
+        let payload: Vec<f32> = wav[44..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect();
+        assert!(payload[..480].iter().all(|s| (s - 0.25).abs() < 1e-6));
+        assert!(payload[480..960].iter().all(|s| (s - 0.75).abs() < 1e-6));
+        assert!(payload[960..].iter().all(|s| s.abs() < 1e-6));
+    }
 }
@@ -6,8 +6,17 @@
 
 pub mod pipewire;
 pub mod mock;
+pub mod clock;
+pub mod registry;
+pub mod dsp;
+pub mod drain;
 
-use crate::buffer::HealthMetrics;
+use std::sync::Arc;
+
+use crate::buffer::{BackpressureListener, HealthMetrics};
+use clock::Clock;
+use crate::mix::{Recorder, RecorderSlot};
+use dsp::{Biquad, BiquadConfig, NoiseGate, NoiseGateConfig, ResampleQuality, Resampler};
 use thiserror::Error;
 
 /// Unique identifier for an audio stream.
@@ -77,6 +86,18 @@ pub enum StreamState {
     Error,
 }
 
+/// What a running playback stream does after the device drains its buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnderrunPolicy {
+    /// Keep running and play silence until data arrives
+    #[default]
+    Silence,
+    /// Return to prebuffering and resume at the high-water mark
+    RePrebuffer,
+    /// Stop the stream
+    Stop,
+}
+
 /// Configuration for creating a stream.
 #[derive(Debug, Clone)]
 pub struct StreamConfig {
@@ -94,6 +115,32 @@ pub struct StreamConfig {
     pub name: String,
     /// Stream direction
     pub direction: StreamDirection,
+    /// Device latency compensation in milliseconds (default: 0).
+    ///
+    /// Added to the reported latency, and for playback also pads (positive)
+    /// or trims (negative) the prebuffer. Results are clamped at zero, so a
+    /// negative offset never yields a negative effective latency.
+    pub latency_offset_ms: i32,
+    /// Fill fraction (0.0 - 1.0) below which a running stream drops back to
+    /// prebuffering (default: 0.0, disabled)
+    pub low_water_frac: f32,
+    /// Fill fraction (0.0 - 1.0) at which a prebuffering stream starts
+    /// running (default: 0.0, use `prebuffer_ms`)
+    pub high_water_frac: f32,
+    /// Noise gate applied when reading from a recording stream
+    /// (default: None, disabled)
+    pub noise_gate: Option<NoiseGateConfig>,
+    /// EQ filter applied when writing to a playback stream
+    /// (default: None, disabled)
+    pub filter: Option<BiquadConfig>,
+    /// Rate of samples written to a playback stream when it differs from
+    /// `sample_rate` (default: None, no conversion)
+    pub source_sample_rate: Option<u32>,
+    /// Interpolation used when converting from `source_sample_rate`
+    /// (default: Fast)
+    pub resample_quality: ResampleQuality,
+    /// Recovery after a playback underrun (default: Silence)
+    pub underrun_policy: UnderrunPolicy,
 }
 
 impl Default for StreamConfig {
@@ -106,6 +153,14 @@ impl Default for StreamConfig {
             prebuffer_ms: 50,
             name: "claude-voice".to_string(),
             direction: StreamDirection::Playback,
+            latency_offset_ms: 0,
+            low_water_frac: 0.0,
+            high_water_frac: 0.0,
+            noise_gate: None,
+            filter: None,
+            source_sample_rate: None,
+            resample_quality: ResampleQuality::Fast,
+            underrun_policy: UnderrunPolicy::Silence,
         }
     }
 }
@@ -113,7 +168,113 @@ impl Default for StreamConfig {
 impl StreamConfig {
     /// Calculate prebuffer size in samples.
     pub fn prebuffer_samples(&self) -> usize {
-        ((self.sample_rate as usize) * (self.prebuffer_ms as usize) / 1000) * (self.channels as usize)
+        ((self.sample_rate as usize) * (self.effective_prebuffer_ms() as usize) / 1000) * (self.channels as usize)
+    }
+
+    /// Prebuffer duration with the latency offset applied (playback only).
+    pub fn effective_prebuffer_ms(&self) -> u32 {
+        match self.direction {
+            StreamDirection::Playback => {
+                (self.prebuffer_ms as i64 + self.latency_offset_ms as i64).max(0) as u32
+            }
+            StreamDirection::Recording => self.prebuffer_ms,
+        }
+    }
+
+    /// Estimated stream latency in milliseconds, including the latency offset.
+    pub fn latency_ms(&self) -> u32 {
+        let base = self.buffer_size_ms as i64 + self.prebuffer_ms as i64;
+        (base + self.latency_offset_ms as i64).max(0) as u32
+    }
+
+    /// Fill level in samples at which a prebuffering stream starts running.
+    pub fn high_water_samples(&self, capacity: usize) -> usize {
+        if self.high_water_frac > 0.0 {
+            (capacity as f32 * self.high_water_frac.min(1.0)) as usize
+        } else {
+            self.prebuffer_samples()
+        }
+    }
+
+    /// Fill level in samples below which a running stream re-enters prebuffering.
+    pub fn low_water_samples(&self, capacity: usize) -> usize {
+        (capacity as f32 * self.low_water_frac.clamp(0.0, 1.0)) as usize
+    }
+
+    /// Check that the water marks are fractions and leave room for hysteresis
+    /// in a buffer of `capacity` samples.
+    ///
+    /// The marks are compared in samples, so a low water mark is also checked
+    /// against the prebuffer threshold used when `high_water_frac` is unset.
+    pub fn validate_water_marks(&self, capacity: usize) -> Result<()> {
+        let in_range = |frac: f32| (0.0..=1.0).contains(&frac);
+        if !in_range(self.low_water_frac) || !in_range(self.high_water_frac) {
+            return Err(BackendError::InvalidConfig(
+                "Water marks must be between 0.0 and 1.0".into(),
+            ));
+        }
+        if self.low_water_frac > 0.0
+            && self.low_water_samples(capacity) >= self.high_water_samples(capacity)
+        {
+            return Err(BackendError::InvalidConfig(
+                "Low water mark must be below high water mark".into(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Build the read-path noise gate, if one is enabled on a recording stream.
+    pub fn build_noise_gate(&self) -> Option<NoiseGate> {
+        match (self.direction, self.noise_gate) {
+            (StreamDirection::Recording, Some(gate)) => {
+                Some(NoiseGate::new(gate, self.sample_rate, self.channels))
+            }
+            _ => None,
+        }
+    }
+
+    /// Build the write-path EQ filter, if one is enabled on a playback stream.
+    pub fn build_filter(&self) -> Option<Biquad> {
+        match (self.direction, self.filter) {
+            (StreamDirection::Playback, Some(filter)) => {
+                Some(Biquad::new(filter, self.sample_rate, self.channels))
+            }
+            _ => None,
+        }
+    }
+
+    /// Build the write-path resampler, if a playback stream is fed at a
+    /// different rate than it plays.
+    pub fn build_resampler(&self) -> Option<Resampler> {
+        match (self.direction, self.source_sample_rate) {
+            (StreamDirection::Playback, Some(rate)) if rate != self.sample_rate => Some(
+                Resampler::new(self.resample_quality, rate, self.sample_rate, self.channels),
+            ),
+            _ => None,
+        }
+    }
+
+    /// Configuration for a tee of a playback stream with this config.
+    ///
+    /// The tee holds the audio as written to the source's ring buffer, so
+    /// it keeps the stream rate and format and has no DSP of its own. It is
+    /// read like a recording stream.
+    pub fn tee_config(&self) -> Result<StreamConfig> {
+        if self.direction != StreamDirection::Playback {
+            return Err(BackendError::InvalidConfig(
+                "Only playback streams can be teed".into(),
+            ));
+        }
+        Ok(StreamConfig {
+            name: format!("{}-tee", self.name),
+            direction: StreamDirection::Recording,
+            low_water_frac: 0.0,
+            high_water_frac: 0.0,
+            noise_gate: None,
+            filter: None,
+            source_sample_rate: None,
+            ..self.clone()
+        })
     }
 
     /// Calculate buffer size in samples.
@@ -144,6 +305,20 @@ pub struct AudioDevice {
     pub channels: u32,
 }
 
+/// Features a backend supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Capabilities {
+    /// Audio reaches a real output/input device
+    pub hardware_io: bool,
+    /// Volume is applied by the device or sound server rather than by
+    /// scaling samples in software
+    pub hardware_volume: bool,
+    /// Devices are enumerated from the system rather than fixed placeholders
+    pub device_enumeration: bool,
+    /// Recording streams can be created
+    pub recording: bool,
+}
+
 /// Backend errors.
 #[derive(Error, Debug)]
 pub enum BackendError {
@@ -172,8 +347,48 @@ pub enum BackendError {
     Internal(String),
 }
 
+impl BackendError {
+    /// Machine-readable code for this kind of error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::NotAvailable(_) => "ERR_NOT_AVAILABLE",
+            Self::ConnectionFailed(_) => "ERR_CONNECTION_FAILED",
+            Self::StreamNotFound(_) => "ERR_STREAM_NOT_FOUND",
+            Self::BufferOverrun { .. } => "ERR_BUFFER_OVERRUN",
+            Self::BufferUnderrun { .. } => "ERR_BUFFER_UNDERRUN",
+            Self::InvalidConfig(_) => "ERR_INVALID_CONFIG",
+            Self::InvalidState { .. } => "ERR_INVALID_STATE",
+            Self::Internal(_) => "ERR_INTERNAL",
+        }
+    }
+
+    /// Message prefixed with the error code, e.g.
+    /// `ERR_STREAM_NOT_FOUND: Stream not found: ...`.
+    pub fn coded_message(&self) -> String {
+        format!("{}: {}", self.code(), self)
+    }
+}
+
 pub type Result<T> = std::result::Result<T, BackendError>;
 
+/// Default cap on open streams per backend.
+pub const DEFAULT_MAX_STREAMS: usize = 64;
+
+/// Fail with `InvalidConfig` if opening another stream would exceed `max`.
+pub fn check_stream_limit(open: usize, max: usize) -> Result<()> {
+    if open >= max {
+        return Err(BackendError::InvalidConfig(format!(
+            "Stream limit reached: {} of {} streams open",
+            open, max
+        )));
+    }
+    Ok(())
+}
+
+/// Callback for system default device changes, given the direction and the
+/// new device's identifier.
+pub type DefaultDeviceListener = Arc<dyn Fn(StreamDirection, &str) + Send + Sync>;
+
 /// Trait for audio backend implementations.
 ///
 /// All audio backends (PipeWire, PulseAudio, ALSA, Mock) implement this trait.
@@ -185,6 +400,9 @@ pub trait Backend: Send + Sync {
     /// Check if backend is available and can be initialized.
     fn is_available(&self) -> bool;
 
+    /// Features this backend supports.
+    fn capabilities(&self) -> Capabilities;
+
     /// Initialize the backend.
     fn initialize(&mut self) -> Result<()>;
 
@@ -197,6 +415,22 @@ pub trait Backend: Send + Sync {
     /// Destroy a stream and release its resources.
     fn destroy_stream(&mut self, handle: StreamHandle) -> Result<()>;
 
+    /// Create a read-only stream receiving a copy of every write to the
+    /// playback stream `source`.
+    ///
+    /// The tee is not connected to a device; it is read like a recording
+    /// stream and outlives its source.
+    fn tee(&mut self, source: StreamHandle) -> Result<StreamHandle>;
+
+    /// Handles of all streams that have not been destroyed.
+    fn stream_handles(&self) -> Vec<StreamHandle>;
+
+    /// Cap the number of open streams (default: `DEFAULT_MAX_STREAMS`).
+    ///
+    /// Streams already open are kept; creation fails until enough are
+    /// destroyed to get back under the cap.
+    fn set_max_streams(&mut self, max: usize);
+
     /// Get current stream state.
     fn get_state(&self, handle: StreamHandle) -> Result<StreamState>;
 
@@ -212,6 +446,34 @@ pub trait Backend: Send + Sync {
     /// Resume a paused stream.
     fn resume(&mut self, handle: StreamHandle) -> Result<()>;
 
+    /// Pause every `Running` stream.
+    ///
+    /// Returns the number of streams paused.
+    fn pause_all(&mut self) -> Result<usize> {
+        let mut paused = 0;
+        for handle in self.stream_handles() {
+            if self.get_state(handle)? == StreamState::Running {
+                self.pause(handle)?;
+                paused += 1;
+            }
+        }
+        Ok(paused)
+    }
+
+    /// Resume every `Paused` stream.
+    ///
+    /// Returns the number of streams resumed.
+    fn resume_all(&mut self) -> Result<usize> {
+        let mut resumed = 0;
+        for handle in self.stream_handles() {
+            if self.get_state(handle)? == StreamState::Paused {
+                self.resume(handle)?;
+                resumed += 1;
+            }
+        }
+        Ok(resumed)
+    }
+
     /// Write audio samples to a playback stream.
     ///
     /// Returns the number of samples actually written.
@@ -228,11 +490,39 @@ pub trait Backend: Send + Sync {
     /// Get current stream volume.
     fn get_volume(&self, handle: StreamHandle) -> Result<f32>;
 
+    /// Frames consumed since the stream was last started or stopped.
+    ///
+    /// Counts frames pulled by the device for playback, and frames returned
+    /// by `read` for recording.
+    fn get_position(&self, handle: StreamHandle) -> Result<u64>;
+
     /// Get buffer health metrics for a stream.
     fn get_health(&self, handle: StreamHandle) -> Result<HealthMetrics>;
 
-    /// Wait for a playback stream to finish all queued audio.
-    fn drain(&self, handle: StreamHandle) -> Result<()>;
+    /// Notify `listener` when a stream's fill level crosses `high` upward or
+    /// `low` downward (0.0 - 1.0). `None` removes the listener.
+    fn set_backpressure_listener(
+        &self,
+        handle: StreamHandle,
+        high: f32,
+        low: f32,
+        listener: Option<BackpressureListener>,
+    ) -> Result<()>;
+
+    /// Advance a drain by one poll, returning whether the stream is empty.
+    ///
+    /// A running or prebuffering stream switches to `Draining` and plays out
+    /// its tail; once empty it returns to `Idle`, ready for `start`.
+    /// [`drain::drain`] calls this every [`drain::DRAIN_POLL`], so the
+    /// backend is not held for the whole drain.
+    fn drain_step(&self, handle: StreamHandle) -> Result<bool>;
+
+    /// Abandon a drain that timed out: a stream still `Draining` goes back
+    /// to `Running` with its queued audio.
+    fn end_drain(&self, handle: StreamHandle) -> Result<()>;
+
+    /// Time source the backend waits on.
+    fn clock(&self) -> Arc<dyn Clock>;
 
     /// List available playback devices.
     fn list_playback_devices(&self) -> Result<Vec<AudioDevice>>;
@@ -245,4 +535,131 @@ pub trait Backend: Send + Sync {
 
     /// Get default recording device.
     fn default_recording_device(&self) -> Result<AudioDevice>;
+
+    /// Attachment point for capturing rendered playback audio.
+    fn recorder(&self) -> &RecorderSlot;
+
+    /// Start capturing the mixed playback output, replacing any recording
+    /// in progress. Streams in another format are not captured.
+    fn start_recording(&self, sample_rate: u32, channels: u32) {
+        let recorder = Recorder::new(sample_rate, channels, &self.stream_handles());
+        self.recorder().attach(recorder);
+    }
+
+    /// Stop capturing, returning the recording if one was in progress.
+    fn stop_recording(&self) -> Option<Arc<Recorder>> {
+        self.recorder().detach()
+    }
+
+    /// Register (or with `None`, remove) a callback for changes of the
+    /// system default playback or recording device.
+    ///
+    /// Backends without real devices never invoke it.
+    fn set_default_device_listener(&mut self, listener: Option<DefaultDeviceListener>) {
+        let _ = listener;
+    }
+}
+
+/// Create and initialize a backend by name: "auto", "pipewire", or "mock".
+///
+/// "auto" falls back to the mock backend when PipeWire cannot be started.
+/// "pipewire" does the same unless `strict` is set, in which case the
+/// failure is returned as `BackendError::NotAvailable`.
+pub fn create_backend(name: &str, strict: bool) -> Result<Box<dyn Backend>> {
+    let mut backend: Box<dyn Backend> = match name {
+        "mock" => Box::new(mock::MockBackend::new()),
+        "pipewire" | "auto" => match start_pipewire() {
+            Ok(backend) => return Ok(backend),
+            Err(e) if strict && name == "pipewire" => {
+                return Err(match e {
+                    BackendError::NotAvailable(_) => e,
+                    other => BackendError::NotAvailable(other.to_string()),
+                });
+            }
+            Err(e) => {
+                eprintln!("PipeWire not available: {}, using mock backend", e);
+                Box::new(mock::MockBackend::new())
+            }
+        },
+        _ => {
+            return Err(BackendError::InvalidConfig(format!(
+                "Unknown backend: {}",
+                name
+            )));
+        }
+    };
+
+    backend.initialize()?;
+    Ok(backend)
+}
+
+/// Construct and initialize the PipeWire backend.
+fn start_pipewire() -> Result<Box<dyn Backend>> {
+    #[cfg(target_os = "linux")]
+    {
+        let mut backend = crate::pipewire_backend::PipeWireBackend::new()?;
+        backend.initialize()?;
+        Ok(Box::new(backend))
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        Err(BackendError::NotAvailable(
+            "PipeWire only available on Linux".into(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_create_mock_backend() {
+        let mut backend = create_backend("mock", true).unwrap();
+        assert_eq!(backend.name(), "mock");
+
+        // Returned already initialized
+        assert!(backend.create_stream(StreamConfig::default()).is_ok());
+    }
+
+    #[test]
+    fn test_error_codes_distinguish_variants() {
+        let not_found = BackendError::StreamNotFound(StreamHandle::new(7));
+        let invalid = BackendError::InvalidConfig("bad rate".into());
+
+        assert_eq!(not_found.code(), "ERR_STREAM_NOT_FOUND");
+        assert_eq!(invalid.code(), "ERR_INVALID_CONFIG");
+        assert!(not_found.coded_message().starts_with("ERR_STREAM_NOT_FOUND: "));
+        assert!(invalid.coded_message().starts_with("ERR_INVALID_CONFIG: "));
+    }
+
+    #[test]
+    fn test_create_unknown_backend() {
+        assert!(matches!(
+            create_backend("alsa", false),
+            Err(BackendError::InvalidConfig(_))
+        ));
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_pipewire_capabilities_differ_from_mock() {
+        let pipewire = crate::pipewire_backend::PipeWireBackend::CAPABILITIES;
+        assert_ne!(pipewire, mock::MockBackend::CAPABILITIES);
+        assert!(pipewire.hardware_io);
+        assert!(pipewire.recording);
+    }
+
+    #[cfg(not(target_os = "linux"))]
+    #[test]
+    fn test_strict_pipewire_unavailable() {
+        assert!(matches!(
+            create_backend("pipewire", true),
+            Err(BackendError::NotAvailable(_))
+        ));
+
+        // Non-strict requests still fall back to the mock backend
+        assert_eq!(create_backend("pipewire", false).unwrap().name(), "mock");
+        assert_eq!(create_backend("auto", true).unwrap().name(), "mock");
+    }
 }
@@ -0,0 +1,89 @@
+//! Name-based stream lookup.
+//!
+//! Lets callers address streams by the name they were created with instead
+//! of tracking numeric handles. Names are unique among active streams.
+
+use std::collections::HashMap;
+use parking_lot::Mutex;
+
+use crate::backend::{BackendError, Result, StreamHandle};
+
+/// Thread-safe map from stream name to handle.
+#[derive(Default)]
+pub struct StreamRegistry {
+    names: Mutex<HashMap<String, StreamHandle>>,
+}
+
+impl StreamRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Associate `name` with `handle`.
+    ///
+    /// Fails with `InvalidConfig` if another active stream already uses the name.
+    pub fn register(&self, name: &str, handle: StreamHandle) -> Result<()> {
+        let mut names = self.names.lock();
+        if names.contains_key(name) {
+            return Err(BackendError::InvalidConfig(format!(
+                "Stream name already in use: {}",
+                name
+            )));
+        }
+        names.insert(name.to_string(), handle);
+        Ok(())
+    }
+
+    /// Remove whichever name maps to `handle`, returning it.
+    pub fn unregister(&self, handle: StreamHandle) -> Option<String> {
+        let mut names = self.names.lock();
+        let name = names
+            .iter()
+            .find(|(_, &h)| h == handle)
+            .map(|(name, _)| name.clone())?;
+        names.remove(&name);
+        Some(name)
+    }
+
+    /// Handle of the active stream called `name`.
+    pub fn lookup(&self, name: &str) -> Option<StreamHandle> {
+        self.names.lock().get(name).copied()
+    }
+
+    /// Forget all names, e.g. after the backend is replaced.
+    pub fn clear(&self) {
+        self.names.lock().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_register_and_lookup() {
+        let registry = StreamRegistry::new();
+
+        registry.register("tts", StreamHandle::new(1)).unwrap();
+        registry.register("music", StreamHandle::new(2)).unwrap();
+
+        assert_eq!(registry.lookup("tts"), Some(StreamHandle::new(1)));
+        assert_eq!(registry.lookup("music"), Some(StreamHandle::new(2)));
+        assert_eq!(registry.lookup("missing"), None);
+    }
+
+    #[test]
+    fn test_duplicate_name_rejected() {
+        let registry = StreamRegistry::new();
+        registry.register("tts", StreamHandle::new(1)).unwrap();
+
+        let result = registry.register("tts", StreamHandle::new(2));
+        assert!(matches!(result, Err(BackendError::InvalidConfig(_))));
+        assert_eq!(registry.lookup("tts"), Some(StreamHandle::new(1)));
+
+        // Name becomes available again once the stream is gone
+        assert_eq!(registry.unregister(StreamHandle::new(1)).as_deref(), Some("tts"));
+        registry.register("tts", StreamHandle::new(2)).unwrap();
+        assert_eq!(registry.lookup("tts"), Some(StreamHandle::new(2)));
+    }
+}
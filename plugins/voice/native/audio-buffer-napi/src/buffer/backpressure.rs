@@ -0,0 +1,184 @@
+//! Fill-level watermark notifications.
+//!
+//! Signals a producer to slow down when a buffer fills past a high
+//! watermark, and to resume once it drains below a low watermark.
+//! Crossings are detected wherever the fill level changes, including the
+//! audio thread, but listeners only run from `deliver`.
+
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Arc;
+use parking_lot::Mutex;
+
+use crate::backend::{BackendError, Result};
+
+/// Direction of a watermark crossing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BackpressureEvent {
+    /// Fill level rose to the high watermark; the producer should slow down
+    High,
+    /// Fill level fell to the low watermark; the producer may resume
+    Low,
+}
+
+/// Callback invoked on each watermark crossing.
+pub type BackpressureListener = Arc<dyn Fn(BackpressureEvent) + Send + Sync>;
+
+/// Watermark state for one buffer.
+///
+/// `update` is lock-free and allocation-free, so it can run on the audio
+/// thread; it only records the crossing. `deliver` takes the listener lock
+/// and reports it, and must be called from a thread that may block.
+pub struct Backpressure {
+    /// Watermarks as fixed-point (0-1000 representing 0.0-1.0)
+    high: AtomicU32,
+    low: AtomicU32,
+    /// Whether the last crossing was upward
+    above: AtomicBool,
+    /// Value of `above` last reported to the listener
+    reported: AtomicBool,
+    listener: Mutex<Option<BackpressureListener>>,
+}
+
+impl Backpressure {
+    pub fn new() -> Self {
+        Self {
+            // Unreachable until configured
+            high: AtomicU32::new(u32::MAX),
+            low: AtomicU32::new(0),
+            above: AtomicBool::new(false),
+            reported: AtomicBool::new(false),
+            listener: Mutex::new(None),
+        }
+    }
+
+    /// Set the watermarks (0.0 - 1.0) and listener, or remove the listener
+    /// with `None`.
+    ///
+    /// Fails with `InvalidConfig` unless `0.0 <= low < high <= 1.0`.
+    pub fn configure(
+        &self,
+        high: f32,
+        low: f32,
+        listener: Option<BackpressureListener>,
+    ) -> Result<()> {
+        if !(0.0..=1.0).contains(&low) || !(0.0..=1.0).contains(&high) || low >= high {
+            return Err(BackendError::InvalidConfig(format!(
+                "Backpressure watermarks must satisfy 0 <= low < high <= 1, got low={} high={}",
+                low, high
+            )));
+        }
+
+        let mut current = self.listener.lock();
+        self.high.store((high * 1000.0) as u32, Ordering::Relaxed);
+        self.low.store((low * 1000.0) as u32, Ordering::Relaxed);
+        self.above.store(false, Ordering::Relaxed);
+        self.reported.store(false, Ordering::Relaxed);
+        *current = listener;
+        Ok(())
+    }
+
+    /// Check a new fill level (fixed-point) for watermark crossings.
+    ///
+    /// The crossing is reported by the next `deliver`.
+    pub fn update(&self, fill: u32) {
+        let event = if fill >= self.high.load(Ordering::Relaxed) {
+            BackpressureEvent::High
+        } else if fill <= self.low.load(Ordering::Relaxed) {
+            BackpressureEvent::Low
+        } else {
+            return;
+        };
+
+        self.above
+            .store(event == BackpressureEvent::High, Ordering::Release);
+    }
+
+    /// Report the crossing recorded since the last delivery, if any.
+    ///
+    /// A crossing that is undone before delivery, such as a spike above the
+    /// high watermark and back below the low one, is not reported.
+    pub fn deliver(&self) {
+        let above = self.above.load(Ordering::Acquire);
+        // Only the caller that flips the reported state runs the listener
+        if self
+            .reported
+            .compare_exchange(!above, above, Ordering::AcqRel, Ordering::Relaxed)
+            .is_err()
+        {
+            return;
+        }
+
+        let event = if above {
+            BackpressureEvent::High
+        } else {
+            BackpressureEvent::Low
+        };
+        let listener = self.listener.lock().clone();
+        if let Some(listener) = listener {
+            listener(event);
+        }
+    }
+}
+
+impl Default for Backpressure {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fires_once_per_crossing() {
+        let backpressure = Backpressure::new();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&events);
+        backpressure
+            .configure(0.8, 0.2, Some(Arc::new(move |event| seen.lock().push(event))))
+            .unwrap();
+
+        for fill in [100, 500, 850, 900, 500, 150, 100, 900] {
+            backpressure.update(fill);
+            backpressure.deliver();
+        }
+
+        assert_eq!(
+            *events.lock(),
+            vec![BackpressureEvent::High, BackpressureEvent::Low, BackpressureEvent::High]
+        );
+    }
+
+    #[test]
+    fn test_update_defers_listener_to_deliver() {
+        let backpressure = Backpressure::new();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&events);
+        backpressure
+            .configure(0.8, 0.2, Some(Arc::new(move |event| seen.lock().push(event))))
+            .unwrap();
+
+        backpressure.update(900);
+        assert!(events.lock().is_empty());
+
+        backpressure.deliver();
+        backpressure.deliver();
+        assert_eq!(*events.lock(), vec![BackpressureEvent::High]);
+
+        // Undone before delivery: nothing to report
+        backpressure.update(100);
+        backpressure.update(900);
+        backpressure.deliver();
+        assert_eq!(*events.lock(), vec![BackpressureEvent::High]);
+    }
+
+    #[test]
+    fn test_rejects_inverted_watermarks() {
+        let backpressure = Backpressure::new();
+        assert!(matches!(
+            backpressure.configure(0.2, 0.8, None),
+            Err(BackendError::InvalidConfig(_))
+        ));
+    }
+}
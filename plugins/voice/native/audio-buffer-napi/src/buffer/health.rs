@@ -1,10 +1,12 @@
 //! Buffer health monitoring with atomic metrics.
 //!
 //! Tracks buffer fill level, underruns, overruns, and latency.
-//! All operations are lock-free using atomic types.
+//! All operations are lock-free using atomic types, except for delivering
+//! backpressure crossings to the listener.
 
 use std::sync::atomic::{AtomicU32, AtomicU64, AtomicU8, Ordering};
-use crate::backend::StreamState;
+use crate::backend::{Result, StreamState, UnderrunPolicy};
+use crate::buffer::backpressure::{Backpressure, BackpressureListener};
 
 /// Atomic health monitor for real-time metrics.
 pub struct HealthMonitor {
@@ -18,6 +20,8 @@ pub struct HealthMonitor {
     latency_ms: AtomicU32,
     /// Current state (encoded as u8)
     state: AtomicU8,
+    /// Fill-level watermark notifications
+    backpressure: Backpressure,
 }
 
 impl HealthMonitor {
@@ -29,13 +33,35 @@ impl HealthMonitor {
             overrun_count: AtomicU64::new(0),
             latency_ms: AtomicU32::new(0),
             state: AtomicU8::new(StreamState::Idle as u8),
+            backpressure: Backpressure::new(),
         }
     }
 
     /// Update the fill level (0.0 - 1.0).
+    ///
+    /// Safe on the audio thread: watermark crossings are only recorded, and
+    /// reach the listener through `deliver_backpressure`.
     pub fn set_fill_level(&self, level: f32) {
         let fixed = (level.clamp(0.0, 1.0) * 1000.0) as u32;
         self.fill_level.store(fixed, Ordering::Relaxed);
+        self.backpressure.update(fixed);
+    }
+
+    /// Notify `listener` when the fill level crosses `high` upward or `low`
+    /// downward (0.0 - 1.0). `None` removes the listener.
+    pub fn set_backpressure(
+        &self,
+        high: f32,
+        low: f32,
+        listener: Option<BackpressureListener>,
+    ) -> Result<()> {
+        self.backpressure.configure(high, low, listener)
+    }
+
+    /// Report a watermark crossing recorded by `set_fill_level` to the
+    /// backpressure listener. Must not be called on the audio thread.
+    pub fn deliver_backpressure(&self) {
+        self.backpressure.deliver();
     }
 
     /// Get the fill level (0.0 - 1.0).
@@ -91,10 +117,53 @@ impl HealthMonitor {
         }
     }
 
+    /// Apply prebuffer hysteresis for a buffer holding `available` samples.
+    ///
+    /// A running stream below `low` drops back to prebuffering; a
+    /// prebuffering stream at or above `high` resumes running. Other states
+    /// are left alone. Uses compare-and-swap so a concurrent state change
+    /// (e.g. pause) is never overwritten.
+    pub fn apply_water_marks(&self, available: usize, low: usize, high: usize) {
+        let (from, to) = match self.get_state() {
+            StreamState::Running if available < low => {
+                (StreamState::Running, StreamState::Prebuffering)
+            }
+            StreamState::Prebuffering if available >= high => {
+                (StreamState::Prebuffering, StreamState::Running)
+            }
+            _ => return,
+        };
+        let _ = self
+            .state
+            .compare_exchange(from as u8, to as u8, Ordering::AcqRel, Ordering::Acquire);
+    }
+
+    /// Apply `policy` after a running stream underran.
+    ///
+    /// Draining streams are expected to run dry and are left alone, as is
+    /// any state change that raced with the underrun.
+    pub fn apply_underrun(&self, policy: UnderrunPolicy) {
+        let to = match policy {
+            UnderrunPolicy::Silence => return,
+            UnderrunPolicy::RePrebuffer => StreamState::Prebuffering,
+            UnderrunPolicy::Stop => StreamState::Stopped,
+        };
+        let _ = self.state.compare_exchange(
+            StreamState::Running as u8,
+            to as u8,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        );
+    }
+
     /// Get a snapshot of all metrics.
+    ///
+    /// `peak_fill_level` is left at 0.0; the monitor does not see the
+    /// buffer, so backends fill it in.
     pub fn snapshot(&self) -> HealthMetrics {
         HealthMetrics {
             fill_level: self.get_fill_level(),
+            peak_fill_level: 0.0,
             underrun_count: self.get_underrun_count(),
             overrun_count: self.get_overrun_count(),
             latency_ms: self.get_latency(),
@@ -123,6 +192,8 @@ impl Default for HealthMonitor {
 pub struct HealthMetrics {
     /// Buffer fill level (0.0 - 1.0)
     pub fill_level: f32,
+    /// Highest fill level (0.0 - 1.0) since the stream was created or stopped
+    pub peak_fill_level: f32,
     /// Number of underrun events
     pub underrun_count: u64,
     /// Number of overrun events
@@ -189,4 +260,28 @@ mod tests {
         assert_eq!(snapshot.latency_ms, 50);
         assert_eq!(snapshot.state, StreamState::Running);
     }
+
+    #[test]
+    fn test_water_mark_hysteresis() {
+        let health = HealthMonitor::new();
+        health.set_state(StreamState::Prebuffering);
+
+        health.apply_water_marks(50, 20, 80);
+        assert_eq!(health.get_state(), StreamState::Prebuffering);
+
+        health.apply_water_marks(80, 20, 80);
+        assert_eq!(health.get_state(), StreamState::Running);
+
+        // Between the marks a running stream keeps running
+        health.apply_water_marks(50, 20, 80);
+        assert_eq!(health.get_state(), StreamState::Running);
+
+        health.apply_water_marks(19, 20, 80);
+        assert_eq!(health.get_state(), StreamState::Prebuffering);
+
+        // Other states are not touched
+        health.set_state(StreamState::Paused);
+        health.apply_water_marks(0, 20, 80);
+        assert_eq!(health.get_state(), StreamState::Paused);
+    }
 }
@@ -4,9 +4,12 @@
 //! - Lock-free ring buffer for audio samples (SPSC)
 //! - Health monitoring with atomic metrics
 //! - Prebuffering state management
+//! - Backpressure watermark notifications
 
 pub mod ring;
 pub mod health;
+pub mod backpressure;
 
 pub use ring::RingBuffer;
 pub use health::{HealthMonitor, HealthMetrics};
+pub use backpressure::{BackpressureEvent, BackpressureListener};
@@ -24,6 +24,8 @@ pub struct RingBuffer {
     read_pos: AtomicUsize,
     /// Write position (producer)
     write_pos: AtomicUsize,
+    /// Peak occupancy observed after a write
+    high_water: AtomicUsize,
 }
 
 // SAFETY: RingBuffer is designed for SPSC where producer and consumer
@@ -52,14 +54,20 @@ impl RingBuffer {
             mask,
             read_pos: AtomicUsize::new(0),
             write_pos: AtomicUsize::new(0),
+            high_water: AtomicUsize::new(0),
         }
     }
 
     /// Create a ring buffer sized for a given duration at sample rate.
     pub fn for_duration(sample_rate: u32, channels: u32, duration_ms: u32) -> Self {
+        Self::new(Self::capacity_for_duration(sample_rate, channels, duration_ms))
+    }
+
+    /// Capacity in samples of a buffer made by [`Self::for_duration`].
+    pub fn capacity_for_duration(sample_rate: u32, channels: u32, duration_ms: u32) -> usize {
         let samples = (sample_rate as usize) * (channels as usize) * (duration_ms as usize) / 1000;
         // Add some headroom
-        Self::new(samples * 2)
+        (samples * 2).next_power_of_two()
     }
 
     /// Write samples to the buffer.
@@ -82,6 +90,10 @@ impl RingBuffer {
         }
 
         self.write_pos.store(write.wrapping_add(to_write), Ordering::Release);
+
+        let occupancy = write.wrapping_sub(read) + to_write;
+        self.high_water.fetch_max(occupancy, Ordering::Relaxed);
+
         to_write
     }
 
@@ -152,6 +164,21 @@ impl RingBuffer {
         self.available_read() as f32 / self.capacity as f32
     }
 
+    /// Peak number of buffered samples seen since creation or the last reset.
+    pub fn high_water(&self) -> usize {
+        self.high_water.load(Ordering::Relaxed)
+    }
+
+    /// Peak fill percentage (0.0 - 1.0) since creation or the last reset.
+    pub fn peak_fill_percent(&self) -> f32 {
+        self.high_water() as f32 / self.capacity as f32
+    }
+
+    /// Restart peak tracking from zero.
+    pub fn reset_high_water(&self) {
+        self.high_water.store(0, Ordering::Relaxed);
+    }
+
     /// Clear all samples from the buffer.
     pub fn clear(&self) {
         self.read_pos.store(0, Ordering::Release);
@@ -226,6 +253,26 @@ mod tests {
         assert!((buffer.fill_percent() - 1.0).abs() < 0.01);
     }
 
+    #[test]
+    fn test_high_water_tracks_peak_occupancy() {
+        let buffer = RingBuffer::new(16);
+        let mut output = [0.0; 16];
+
+        buffer.write(&[0.0; 6]); // 6
+        buffer.read(&mut output[..4]); // 2
+        buffer.write(&[0.0; 9]); // 11
+        buffer.read(&mut output[..8]); // 3
+        buffer.write(&[0.0; 5]); // 8
+
+        assert_eq!(buffer.high_water(), 11);
+
+        buffer.reset_high_water();
+        assert_eq!(buffer.high_water(), 0);
+
+        buffer.write(&[0.0; 1]); // 9
+        assert_eq!(buffer.high_water(), 9);
+    }
+
     #[test]
     fn test_overwrite_protection() {
         let buffer = RingBuffer::new(4);
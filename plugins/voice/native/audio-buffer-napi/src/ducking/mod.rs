@@ -4,14 +4,20 @@
 //! of background audio when higher priority audio plays.
 
 use std::collections::HashMap;
-use crate::backend::StreamHandle;
+use std::sync::Arc;
+use std::time::Instant;
+use parking_lot::Mutex;
+use crate::backend::clock::{Clock, SystemClock};
+use crate::backend::{Backend, Result, StreamHandle};
 
 /// Information about a stream for ducking calculation.
 #[derive(Debug, Clone)]
 pub struct StreamInfo {
     pub handle: StreamHandle,
     pub priority: u8,  // 0-100, higher = more important
+    /// Volume currently applied to the stream
     pub current_volume: f32,
+    /// Volume the stream plays at when not ducked
     pub target_volume: f32,
 }
 
@@ -21,6 +27,9 @@ pub type VolumeMatrix = HashMap<StreamHandle, f32>;
 /// Trait for ducking strategies.
 pub trait DuckingStrategy: Send + Sync {
     /// Calculate new volumes for all streams based on priorities.
+    ///
+    /// Streams that are not ducked play at their `target_volume`; ducked
+    /// streams get a fraction of it.
     fn calculate_volumes(&self, streams: &[StreamInfo]) -> VolumeMatrix;
 
     /// Get the name of this strategy.
@@ -60,11 +69,11 @@ impl DuckingStrategy for SimpleDucker {
 
         for stream in streams {
             if stream.priority == max_priority {
-                // Highest priority streams stay at full volume
-                result.insert(stream.handle, 1.0);
+                // Highest priority streams keep their own volume
+                result.insert(stream.handle, stream.target_volume);
             } else {
                 // Lower priority streams get ducked
-                result.insert(stream.handle, self.duck_level);
+                result.insert(stream.handle, stream.target_volume * self.duck_level);
             }
         }
 
@@ -77,31 +86,49 @@ impl DuckingStrategy for SimpleDucker {
 }
 
 /// Gradual ducking with fade in/out.
+///
+/// A fade starts whenever a stream's ducked volume changes and runs for
+/// `fade_duration_ms`; each `calculate_volumes` call returns the point
+/// reached so far, so callers re-apply periodically while fading.
 pub struct FadeDucker {
     /// Volume level for ducked streams
     pub duck_level: f32,
     /// Fade duration in milliseconds
     pub fade_duration_ms: u32,
-    /// Current fade progress per stream (0.0 = at target, 1.0 = at current)
-    fade_progress: HashMap<StreamHandle, f32>,
+    clock: Arc<dyn Clock>,
+    /// Fade in progress per stream
+    fades: Mutex<HashMap<StreamHandle, Fade>>,
+}
+
+/// A linear volume ramp.
+struct Fade {
+    from: f32,
+    to: f32,
+    start: Instant,
 }
 
 impl FadeDucker {
     pub fn new(duck_level: f32, fade_duration_ms: u32) -> Self {
+        Self::with_clock(duck_level, fade_duration_ms, Arc::new(SystemClock))
+    }
+
+    /// Create a fade ducker timed by `clock`.
+    pub fn with_clock(duck_level: f32, fade_duration_ms: u32, clock: Arc<dyn Clock>) -> Self {
         Self {
             duck_level: duck_level.clamp(0.0, 1.0),
             fade_duration_ms,
-            fade_progress: HashMap::new(),
+            clock,
+            fades: Mutex::new(HashMap::new()),
         }
     }
 
-    /// Update fade progress based on elapsed time.
-    pub fn update(&mut self, elapsed_ms: u32) {
-        let step = elapsed_ms as f32 / self.fade_duration_ms as f32;
-
-        for progress in self.fade_progress.values_mut() {
-            *progress = (*progress - step).max(0.0);
+    /// Fraction of the fade completed at `now` (0.0 - 1.0).
+    fn progress(&self, fade: &Fade, now: Instant) -> f32 {
+        if self.fade_duration_ms == 0 {
+            return 1.0;
         }
+        let elapsed = now.saturating_duration_since(fade.start).as_secs_f32() * 1000.0;
+        (elapsed / self.fade_duration_ms as f32).min(1.0)
     }
 }
 
@@ -120,17 +147,33 @@ impl DuckingStrategy for FadeDucker {
         }
 
         let max_priority = streams.iter().map(|s| s.priority).max().unwrap_or(0);
+        let now = self.clock.now();
+        let mut fades = self.fades.lock();
+        fades.retain(|handle, _| streams.iter().any(|s| s.handle == *handle));
 
         for stream in streams {
             let target = if stream.priority == max_priority {
-                1.0
+                stream.target_volume
             } else {
-                self.duck_level
+                stream.target_volume * self.duck_level
             };
 
-            // Apply fade if we have progress data
-            let progress = self.fade_progress.get(&stream.handle).copied().unwrap_or(0.0);
-            let volume = stream.current_volume * progress + target * (1.0 - progress);
+            // Start a new fade from wherever the stream is now
+            let fade = fades.entry(stream.handle).or_insert(Fade {
+                from: stream.current_volume,
+                to: target,
+                start: now,
+            });
+            if fade.to != target {
+                *fade = Fade {
+                    from: stream.current_volume,
+                    to: target,
+                    start: now,
+                };
+            }
+
+            let progress = self.progress(fade, now);
+            let volume = fade.from + (fade.to - fade.from) * progress;
 
             result.insert(stream.handle, volume);
         }
@@ -180,7 +223,8 @@ impl DuckingStrategy for ProportionalDucker {
         for stream in streams {
             // Scale volume proportionally to priority
             let normalized = (stream.priority as f32 - min_priority) / range;
-            let volume = self.min_volume + normalized * (1.0 - self.min_volume);
+            let volume =
+                stream.target_volume * (self.min_volume + normalized * (1.0 - self.min_volume));
 
             result.insert(stream.handle, volume);
         }
@@ -193,9 +237,98 @@ impl DuckingStrategy for ProportionalDucker {
     }
 }
 
+/// Look up a built-in strategy by name, using its default settings.
+///
+/// Accepts "simple", "fade" and "proportional".
+pub fn strategy_by_name(name: &str) -> Option<Box<dyn DuckingStrategy>> {
+    match name {
+        "simple" => Some(Box::new(SimpleDucker::default())),
+        "fade" => Some(Box::new(FadeDucker::default())),
+        "proportional" => Some(Box::new(ProportionalDucker::default())),
+        _ => None,
+    }
+}
+
+/// Applies a ducking strategy's volumes to backend streams.
+///
+/// The manager remembers each stream's base volume, the level it plays at
+/// when not ducked, so repeated updates duck from that level rather than
+/// from a volume that is already ducked.
+pub struct DuckingManager {
+    strategy: Box<dyn DuckingStrategy>,
+    backend: Arc<Mutex<Box<dyn Backend>>>,
+    base_volumes: Mutex<HashMap<StreamHandle, f32>>,
+}
+
+impl DuckingManager {
+    pub fn new(strategy: Box<dyn DuckingStrategy>, backend: Arc<Mutex<Box<dyn Backend>>>) -> Self {
+        Self {
+            strategy,
+            backend,
+            base_volumes: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Replace the active strategy.
+    pub fn set_strategy(&mut self, strategy: Box<dyn DuckingStrategy>) {
+        self.strategy = strategy;
+    }
+
+    /// Point the manager at a different backend (e.g. after re-initialization).
+    ///
+    /// Base volumes of the old backend's streams are forgotten.
+    pub fn set_backend(&mut self, backend: Arc<Mutex<Box<dyn Backend>>>) {
+        self.backend = backend;
+        self.base_volumes.lock().clear();
+    }
+
+    /// Record the volume a stream plays at when not ducked, e.g. after the
+    /// user changes it.
+    pub fn set_base_volume(&self, handle: StreamHandle, volume: f32) {
+        self.base_volumes.lock().insert(handle, volume);
+    }
+
+    /// Forget a destroyed stream's base volume.
+    pub fn forget(&self, handle: StreamHandle) {
+        self.base_volumes.lock().remove(&handle);
+    }
+
+    /// Calculate volumes for `streams` and push them to the backend.
+    ///
+    /// A stream's `target_volume` becomes its base volume the first time it
+    /// is seen; after that the stored base volume is used instead.
+    /// Returns the applied volume matrix.
+    pub fn apply(&self, streams: &[StreamInfo]) -> Result<VolumeMatrix> {
+        let streams: Vec<StreamInfo> = {
+            let mut base_volumes = self.base_volumes.lock();
+            streams
+                .iter()
+                .map(|stream| StreamInfo {
+                    target_volume: *base_volumes
+                        .entry(stream.handle)
+                        .or_insert(stream.target_volume),
+                    ..stream.clone()
+                })
+                .collect()
+        };
+        let volumes = self.strategy.calculate_volumes(&streams);
+
+        let mut backend = self.backend.lock();
+        for (&handle, &volume) in &volumes {
+            backend.set_volume(handle, volume)?;
+        }
+
+        Ok(volumes)
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::time::Duration;
+    use crate::backend::clock::MockClock;
+    use crate::backend::mock::MockBackend;
+    use crate::backend::StreamConfig;
 
     fn make_streams(priorities: &[u8]) -> Vec<StreamInfo> {
         priorities
@@ -241,4 +374,98 @@ mod tests {
         // Lowest priority should be min_volume
         assert!((volumes[&StreamHandle::new(1)] - 0.1).abs() < 0.01);
     }
+
+    #[test]
+    fn test_manager_ducks_backend_volume() {
+        let mut mock = MockBackend::new();
+        mock.initialize().unwrap();
+        let voice = mock.create_stream(StreamConfig::default()).unwrap();
+        let music = mock.create_stream(StreamConfig::default()).unwrap();
+
+        let backend: Arc<Mutex<Box<dyn Backend>>> = Arc::new(Mutex::new(Box::new(mock)));
+        let manager = DuckingManager::new(Box::new(SimpleDucker::new(0.3)), backend.clone());
+
+        let streams = [
+            StreamInfo { handle: voice, priority: 100, current_volume: 1.0, target_volume: 1.0 },
+            StreamInfo { handle: music, priority: 20, current_volume: 1.0, target_volume: 1.0 },
+        ];
+        manager.apply(&streams).unwrap();
+
+        let backend = backend.lock();
+        assert!((backend.get_volume(voice).unwrap() - 1.0).abs() < 0.01);
+        assert!((backend.get_volume(music).unwrap() - 0.3).abs() < 0.01);
+    }
+
+    /// Stream infos built from the backend's current volumes, the way
+    /// `AudioManager::update_ducking` builds them.
+    fn infos(
+        backend: &Mutex<Box<dyn Backend>>,
+        priorities: &[(StreamHandle, u8)],
+    ) -> Vec<StreamInfo> {
+        let backend = backend.lock();
+        priorities
+            .iter()
+            .map(|&(handle, priority)| {
+                let volume = backend.get_volume(handle).unwrap();
+                StreamInfo { handle, priority, current_volume: volume, target_volume: volume }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_manager_ducks_then_restores_base_volume() {
+        let mut mock = MockBackend::new();
+        mock.initialize().unwrap();
+        let voice = mock.create_stream(StreamConfig::default()).unwrap();
+        let music = mock.create_stream(StreamConfig::default()).unwrap();
+        mock.set_volume(voice, 0.8).unwrap();
+        mock.set_volume(music, 0.5).unwrap();
+
+        let backend: Arc<Mutex<Box<dyn Backend>>> = Arc::new(Mutex::new(Box::new(mock)));
+        let manager = DuckingManager::new(Box::new(SimpleDucker::new(0.3)), backend.clone());
+
+        // Repeated updates do not duck further
+        for _ in 0..3 {
+            manager.apply(&infos(&backend, &[(voice, 100), (music, 20)])).unwrap();
+        }
+        assert!((backend.lock().get_volume(voice).unwrap() - 0.8).abs() < 0.01);
+        assert!((backend.lock().get_volume(music).unwrap() - 0.15).abs() < 0.01);
+
+        // Voice finished: music returns to its own volume
+        manager.apply(&infos(&backend, &[(music, 20)])).unwrap();
+        assert!((backend.lock().get_volume(music).unwrap() - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_fade_ducker_ramps_to_target() {
+        let clock = Arc::new(MockClock::new());
+        let ducker = FadeDucker::with_clock(0.2, 200, clock.clone());
+        let mut streams = make_streams(&[100, 20]);
+        let music = streams[1].handle;
+
+        // The first call starts the fade at the current volume
+        assert!((ducker.calculate_volumes(&streams)[&music] - 1.0).abs() < 0.01);
+
+        clock.advance(Duration::from_millis(100));
+        let halfway = ducker.calculate_volumes(&streams)[&music];
+        assert!((halfway - 0.6).abs() < 0.01);
+
+        clock.advance(Duration::from_millis(100));
+        assert!((ducker.calculate_volumes(&streams)[&music] - 0.2).abs() < 0.01);
+
+        // Un-ducking fades back up from the ducked level
+        streams[1].current_volume = 0.2;
+        streams.remove(0);
+        clock.advance(Duration::from_millis(50));
+        assert!((ducker.calculate_volumes(&streams)[&music] - 0.2).abs() < 0.01);
+        clock.advance(Duration::from_millis(100));
+        assert!((ducker.calculate_volumes(&streams)[&music] - 0.6).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_strategy_by_name() {
+        assert_eq!(strategy_by_name("fade").unwrap().name(), "fade");
+        assert_eq!(strategy_by_name("proportional").unwrap().name(), "proportional");
+        assert!(strategy_by_name("bogus").is_none());
+    }
 }
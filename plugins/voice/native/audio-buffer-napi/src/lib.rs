@@ -18,17 +18,32 @@
 mod backend;
 mod buffer;
 mod ducking;
+mod mix;
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration;
 
 use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{
+    ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::{Env, JsFunction};
 use napi_derive::napi;
 use parking_lot::Mutex;
 
-use backend::{Backend, BackendError, StreamConfig, StreamDirection, StreamHandle, StreamState, AudioFormat};
+use backend::{
+    Backend, BackendError, Capabilities, DefaultDeviceListener, DEFAULT_MAX_STREAMS,
+    StreamConfig, StreamDirection, StreamHandle, StreamState, AudioFormat, UnderrunPolicy,
+};
+use backend::clock::SystemClock;
+use backend::drain::DRAIN_TIMEOUT;
 use backend::mock::MockBackend;
-use buffer::HealthMetrics;
+use backend::registry::StreamRegistry;
+use backend::dsp::{BiquadConfig, FilterKind, NoiseGateConfig, ResampleQuality};
+use buffer::{BackpressureEvent, BackpressureListener, HealthMetrics};
+use ducking::{DuckingManager, SimpleDucker, StreamInfo};
+use mix::Crossfade;
 
 // Re-export for PipeWire backend (implemented separately)
 #[cfg(target_os = "linux")]
@@ -52,6 +67,32 @@ pub struct JsStreamConfig {
     pub name: Option<String>,
     /// Stream direction: "playback" or "recording"
     pub direction: Option<String>,
+    /// Latency compensation in milliseconds, may be negative (default: 0)
+    pub latency_offset_ms: Option<i32>,
+    /// Fill fraction that sends a running stream back to prebuffering (default: 0, disabled)
+    pub low_water_frac: Option<f64>,
+    /// Fill fraction at which prebuffering ends (default: 0, use prebufferMs)
+    pub high_water_frac: Option<f64>,
+    /// Noise gate threshold (0.0 - 1.0) for recording streams; enables the gate (default: disabled)
+    pub noise_gate_threshold: Option<f64>,
+    /// Noise gate attack time in milliseconds (default: 5)
+    pub noise_gate_attack_ms: Option<f64>,
+    /// Noise gate release time in milliseconds (default: 100)
+    pub noise_gate_release_ms: Option<f64>,
+    /// EQ filter for playback streams: "lowpass", "highpass" or "bandpass" (default: none)
+    pub filter_type: Option<String>,
+    /// EQ filter cutoff or center frequency in Hz (default: 1000)
+    pub filter_cutoff_hz: Option<f64>,
+    /// EQ filter quality factor (default: 0.707)
+    pub filter_q: Option<f64>,
+    /// Rate of samples written to a playback stream, if it differs from
+    /// `sampleRate` (default: same as `sampleRate`)
+    pub source_sample_rate: Option<u32>,
+    /// Resampler quality: "fast", "medium" or "high" (default: "fast")
+    pub resample_quality: Option<String>,
+    /// Playback underrun recovery: "silence", "reprebuffer" or "stop"
+    /// (default: "silence")
+    pub underrun_policy: Option<String>,
 }
 
 impl From<JsStreamConfig> for StreamConfig {
@@ -67,6 +108,39 @@ impl From<JsStreamConfig> for StreamConfig {
             _ => StreamDirection::Playback,
         };
 
+        let noise_gate = js.noise_gate_threshold.map(|threshold| {
+            let defaults = NoiseGateConfig::default();
+            NoiseGateConfig {
+                threshold: threshold as f32,
+                attack_ms: js.noise_gate_attack_ms.map_or(defaults.attack_ms, |ms| ms as f32),
+                release_ms: js.noise_gate_release_ms.map_or(defaults.release_ms, |ms| ms as f32),
+            }
+        });
+
+        let filter_kind = match js.filter_type.as_deref() {
+            Some("lowpass") => Some(FilterKind::Lowpass),
+            Some("highpass") => Some(FilterKind::Highpass),
+            Some("bandpass") => Some(FilterKind::Bandpass),
+            _ => None,
+        };
+        let filter = filter_kind.map(|kind| BiquadConfig {
+            kind,
+            cutoff_hz: js.filter_cutoff_hz.unwrap_or(1000.0) as f32,
+            q: js.filter_q.unwrap_or(0.707) as f32,
+        });
+
+        let resample_quality = match js.resample_quality.as_deref() {
+            Some("medium") => ResampleQuality::Medium,
+            Some("high") => ResampleQuality::High,
+            _ => ResampleQuality::Fast,
+        };
+
+        let underrun_policy = match js.underrun_policy.as_deref() {
+            Some("reprebuffer") => UnderrunPolicy::RePrebuffer,
+            Some("stop") => UnderrunPolicy::Stop,
+            _ => UnderrunPolicy::Silence,
+        };
+
         StreamConfig {
             sample_rate: js.sample_rate.unwrap_or(48000),
             channels: js.channels.unwrap_or(1),
@@ -75,6 +149,14 @@ impl From<JsStreamConfig> for StreamConfig {
             prebuffer_ms: js.prebuffer_ms.unwrap_or(50),
             name: js.name.unwrap_or_else(|| "claude-voice".to_string()),
             direction,
+            latency_offset_ms: js.latency_offset_ms.unwrap_or(0),
+            low_water_frac: js.low_water_frac.unwrap_or(0.0) as f32,
+            high_water_frac: js.high_water_frac.unwrap_or(0.0) as f32,
+            noise_gate,
+            filter,
+            source_sample_rate: js.source_sample_rate,
+            resample_quality,
+            underrun_policy,
         }
     }
 }
@@ -85,6 +167,8 @@ impl From<JsStreamConfig> for StreamConfig {
 pub struct JsHealthMetrics {
     /// Buffer fill level (0.0 - 1.0)
     pub fill_level: f64,
+    /// Highest fill level (0.0 - 1.0) since the stream was created or stopped
+    pub peak_fill_level: f64,
     /// Number of underrun events
     pub underrun_count: u32,
     /// Number of overrun events
@@ -109,6 +193,7 @@ impl From<HealthMetrics> for JsHealthMetrics {
 
         JsHealthMetrics {
             fill_level: metrics.fill_level as f64,
+            peak_fill_level: metrics.peak_fill_level as f64,
             underrun_count: metrics.underrun_count as u32,
             overrun_count: metrics.overrun_count as u32,
             latency_ms: metrics.latency_ms,
@@ -142,13 +227,72 @@ impl From<backend::AudioDevice> for JsAudioDevice {
     }
 }
 
+/// System default device change reported to TypeScript.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct JsDefaultDeviceChange {
+    /// "playback" or "recording"
+    pub direction: String,
+    /// Identifier of the new default device
+    pub device_id: String,
+}
+
+/// Features supported by the active backend.
+#[napi(object)]
+#[derive(Debug, Clone)]
+pub struct JsCapabilities {
+    /// Audio reaches a real output/input device
+    pub hardware_io: bool,
+    /// Volume is applied by the device or sound server
+    pub hardware_volume: bool,
+    /// Devices are enumerated from the system
+    pub device_enumeration: bool,
+    /// Recording streams can be created
+    pub recording: bool,
+}
+
+impl From<Capabilities> for JsCapabilities {
+    fn from(caps: Capabilities) -> Self {
+        JsCapabilities {
+            hardware_io: caps.hardware_io,
+            hardware_volume: caps.hardware_volume,
+            device_enumeration: caps.device_enumeration,
+            recording: caps.recording,
+        }
+    }
+}
+
 /// Convert backend errors to napi errors.
 impl From<BackendError> for napi::Error {
     fn from(err: BackendError) -> Self {
-        napi::Error::new(napi::Status::GenericFailure, format!("{}", err))
+        napi::Error::new(napi::Status::GenericFailure, err.coded_message())
+    }
+}
+
+/// Convert a `create_backend` error, reporting an unknown backend name as
+/// an invalid argument. The message keeps its error code either way.
+fn backend_creation_error(err: BackendError) -> napi::Error {
+    match err {
+        BackendError::InvalidConfig(_) => {
+            napi::Error::new(napi::Status::InvalidArg, err.coded_message())
+        }
+        err => napi::Error::from(err),
     }
 }
 
+/// Run backend work that sleeps, such as a timed fade or a drain, on
+/// tokio's blocking pool so it does not hold up an async worker thread.
+async fn run_blocking<T, F>(work: F) -> Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> backend::Result<T> + Send + 'static,
+{
+    napi::tokio::task::spawn_blocking(work)
+        .await
+        .map_err(|e| napi::Error::from_reason(format!("Blocking task failed: {}", e)))?
+        .map_err(napi::Error::from)
+}
+
 /// The main audio manager class exposed to TypeScript.
 ///
 /// Usage from TypeScript:
@@ -167,6 +311,13 @@ impl From<BackendError> for napi::Error {
 pub struct AudioManager {
     backend: Arc<Mutex<Box<dyn Backend>>>,
     initialized: bool,
+    ducking: DuckingManager,
+    priorities: Mutex<HashMap<u32, u8>>,
+    names: StreamRegistry,
+    /// Kept so the subscription survives re-initialization
+    device_listener: Mutex<Option<DefaultDeviceListener>>,
+    /// Stream cap, re-applied to each new backend
+    max_streams: Mutex<usize>,
 }
 
 #[napi]
@@ -176,51 +327,36 @@ impl AudioManager {
     /// The manager is not initialized until `initialize()` is called.
     #[napi(constructor)]
     pub fn new() -> Self {
+        let backend: Arc<Mutex<Box<dyn Backend>>> =
+            Arc::new(Mutex::new(Box::new(MockBackend::new())));
         Self {
-            backend: Arc::new(Mutex::new(Box::new(MockBackend::new()))),
+            ducking: DuckingManager::new(Box::new(SimpleDucker::default()), backend.clone()),
+            backend,
             initialized: false,
+            priorities: Mutex::new(HashMap::new()),
+            names: StreamRegistry::new(),
+            device_listener: Mutex::new(None),
+            max_streams: Mutex::new(DEFAULT_MAX_STREAMS),
         }
     }
 
     /// Initialize the audio manager with the specified backend.
     ///
     /// @param backend - Backend name: "auto", "pipewire", or "mock"
+    /// @param strict - Fail instead of falling back to mock when "pipewire"
+    ///                 is requested but unavailable (default: false)
     #[napi]
-    pub async fn initialize(&mut self, backend_name: Option<String>) -> Result<()> {
+    pub async fn initialize(
+        &mut self,
+        backend_name: Option<String>,
+        strict: Option<bool>,
+    ) -> Result<()> {
         let backend_name = backend_name.unwrap_or_else(|| "auto".to_string());
 
-        let mut backend: Box<dyn Backend> = match backend_name.as_str() {
-            "mock" => Box::new(MockBackend::new()),
-            "pipewire" | "auto" => {
-                #[cfg(target_os = "linux")]
-                {
-                    // Try PipeWire first, fall back to mock
-                    match pipewire_backend::PipeWireBackend::new() {
-                        Ok(pw) => Box::new(pw),
-                        Err(e) => {
-                            eprintln!("PipeWire not available: {}, using mock backend", e);
-                            Box::new(MockBackend::new())
-                        }
-                    }
-                }
-                #[cfg(not(target_os = "linux"))]
-                {
-                    eprintln!("PipeWire only available on Linux, using mock backend");
-                    Box::new(MockBackend::new())
-                }
-            }
-            _ => {
-                return Err(napi::Error::new(
-                    napi::Status::InvalidArg,
-                    format!("Unknown backend: {}", backend_name),
-                ));
-            }
-        };
-
-        backend.initialize().map_err(|e| napi::Error::from(e))?;
-        self.backend = Arc::new(Mutex::new(backend));
-        self.initialized = true;
+        let backend = backend::create_backend(&backend_name, strict.unwrap_or(false))
+            .map_err(backend_creation_error)?;
 
+        self.install_backend(backend);
         Ok(())
     }
 
@@ -246,28 +382,200 @@ impl AudioManager {
         self.backend.lock().is_available()
     }
 
+    /// Subscribe to system default device changes.
+    ///
+    /// Streams follow the new default automatically; the callback lets the
+    /// caller react, e.g. by re-querying devices. Replaces any previous
+    /// callback and stays registered across `initialize`. Backends without
+    /// real devices never call it.
+    ///
+    /// @param callback - Called with a `JsDefaultDeviceChange`
+    #[napi(ts_args_type = "callback: (change: JsDefaultDeviceChange) => void")]
+    pub fn on_default_device_changed(&self, env: Env, callback: JsFunction) -> Result<()> {
+        let mut tsfn: ThreadsafeFunction<JsDefaultDeviceChange, ErrorStrategy::Fatal> = callback
+            .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<JsDefaultDeviceChange>| {
+                Ok(vec![ctx.value])
+            })?;
+        // A subscription alone must not keep Node running
+        tsfn.unref(&env)?;
+
+        let listener: DefaultDeviceListener = Arc::new(move |direction, device_id| {
+            let direction = match direction {
+                StreamDirection::Playback => "playback",
+                StreamDirection::Recording => "recording",
+            };
+            tsfn.call(
+                JsDefaultDeviceChange {
+                    direction: direction.to_string(),
+                    device_id: device_id.to_string(),
+                },
+                ThreadsafeFunctionCallMode::NonBlocking,
+            );
+        });
+        self.subscribe_default_device(listener);
+        Ok(())
+    }
+
+    /// Subscribe to backpressure on a stream's buffer.
+    ///
+    /// The callback receives "high" when the fill level rises to `high`
+    /// (the producer should slow down) and "low" when it falls back to
+    /// `low` (it may resume). Replaces any previous callback on the stream.
+    ///
+    /// @param handle - Stream handle
+    /// @param high - Upper watermark (0.0 - 1.0)
+    /// @param low - Lower watermark (0.0 - 1.0), below `high`
+    /// @param callback - Called with "high" or "low"
+    #[napi(ts_args_type = "handle: number, high: number, low: number, callback: (event: string) => void")]
+    pub fn on_backpressure(
+        &self,
+        env: Env,
+        handle: u32,
+        high: f64,
+        low: f64,
+        callback: JsFunction,
+    ) -> Result<()> {
+        let mut tsfn: ThreadsafeFunction<String, ErrorStrategy::Fatal> = callback
+            .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<String>| {
+                Ok(vec![ctx.value])
+            })?;
+        tsfn.unref(&env)?;
+
+        let listener: BackpressureListener = Arc::new(move |event| {
+            let event = match event {
+                BackpressureEvent::High => "high",
+                BackpressureEvent::Low => "low",
+            };
+            tsfn.call(event.to_string(), ThreadsafeFunctionCallMode::NonBlocking);
+        });
+        self.backend
+            .lock()
+            .set_backpressure_listener(
+                StreamHandle::new(handle),
+                high as f32,
+                low as f32,
+                Some(listener),
+            )
+            .map_err(napi::Error::from)
+    }
+
+    /// Start capturing the mixed playback output.
+    ///
+    /// Only streams matching the recording's rate and channel count are
+    /// captured. Replaces any recording in progress.
+    ///
+    /// @param sampleRate - Recording sample rate (default: 48000)
+    /// @param channels - Recording channel count (default: 1)
+    #[napi]
+    pub fn start_recording(&self, sample_rate: Option<u32>, channels: Option<u32>) {
+        self.backend
+            .lock()
+            .start_recording(sample_rate.unwrap_or(48000), channels.unwrap_or(1));
+    }
+
+    /// Stop capturing and return the recording as a WAV file.
+    ///
+    /// @param format - Sample format: "f32le", "s16le", or "s32le" (default: "f32le")
+    /// @returns WAV file bytes
+    #[napi]
+    pub fn stop_recording(&self, format: Option<String>) -> Result<Buffer> {
+        let format = match format.as_deref() {
+            Some("s16le") => AudioFormat::S16LE,
+            Some("s32le") => AudioFormat::S32LE,
+            _ => AudioFormat::F32LE,
+        };
+        let recorder = self
+            .backend
+            .lock()
+            .stop_recording()
+            .ok_or_else(|| {
+                napi::Error::new(napi::Status::GenericFailure, "Not recording".to_string())
+            })?;
+        Ok(recorder.to_wav(format).into())
+    }
+
+    /// Get the features supported by the current backend.
+    #[napi]
+    pub fn get_capabilities(&self) -> JsCapabilities {
+        self.backend.lock().capabilities().into()
+    }
+
     /// Create a new audio stream.
     ///
+    /// An explicit `name` must be unique among active streams and can be
+    /// resolved later with `handleForName`.
+    ///
     /// @param config - Stream configuration
     /// @returns Stream handle (number)
     #[napi]
     pub async fn create_stream(&self, config: Option<JsStreamConfig>) -> Result<u32> {
+        let name = config.as_ref().and_then(|c| c.name.clone());
         let config: StreamConfig = config.unwrap_or_default().into();
-        let handle = self
-            .backend
-            .lock()
+
+        let mut backend = self.backend.lock();
+        if let Some(name) = &name {
+            if self.names.lookup(name).is_some() {
+                return Err(napi::Error::from(BackendError::InvalidConfig(format!(
+                    "Stream name already in use: {}",
+                    name
+                ))));
+            }
+        }
+
+        let handle = backend
             .create_stream(config)
             .map_err(|e| napi::Error::from(e))?;
+        if let Some(name) = &name {
+            // Cannot collide: creation is serialized by the backend lock
+            self.names.register(name, handle)?;
+        }
         Ok(handle.id())
     }
 
+    /// Limit how many streams may be open at once (default: 64).
+    ///
+    /// Tees count toward the limit. Lowering it below the number of open
+    /// streams keeps them, but creation fails until enough are destroyed.
+    #[napi]
+    pub fn set_max_streams(&self, max: u32) {
+        *self.max_streams.lock() = max as usize;
+        self.backend.lock().set_max_streams(max as usize);
+    }
+
+    /// Look up the handle of an active stream by the name it was created with.
+    #[napi]
+    pub fn handle_for_name(&self, name: String) -> Option<u32> {
+        self.names.lookup(&name).map(|handle| handle.id())
+    }
+
     /// Destroy a stream.
     #[napi]
     pub async fn destroy_stream(&self, handle: u32) -> Result<()> {
         self.backend
             .lock()
             .destroy_stream(StreamHandle::new(handle))
-            .map_err(|e| napi::Error::from(e))
+            .map_err(|e| napi::Error::from(e))?;
+        // Only a stream that is really gone releases its name and priority
+        self.priorities.lock().remove(&handle);
+        self.ducking.forget(StreamHandle::new(handle));
+        self.names.unregister(StreamHandle::new(handle));
+        Ok(())
+    }
+
+    /// Mirror a playback stream into a new read-only stream.
+    ///
+    /// Every write to the source is copied into the tee, which is read
+    /// like a recording stream (e.g. to capture playback to a file).
+    ///
+    /// @param sourceHandle - Playback stream to mirror
+    /// @returns Handle of the tee stream
+    #[napi]
+    pub fn tee(&self, source_handle: u32) -> Result<u32> {
+        self.backend
+            .lock()
+            .tee(StreamHandle::new(source_handle))
+            .map(|handle| handle.id())
+            .map_err(napi::Error::from)
     }
 
     /// Get the current state of a stream.
@@ -327,6 +635,48 @@ impl AudioManager {
             .map_err(|e| napi::Error::from(e))
     }
 
+    /// Pause every running stream under a single backend lock.
+    ///
+    /// @returns Number of streams paused
+    #[napi]
+    pub async fn pause_all(&self) -> Result<u32> {
+        let paused = self.backend.lock().pause_all()?;
+        Ok(paused as u32)
+    }
+
+    /// Resume every paused stream under a single backend lock.
+    ///
+    /// @returns Number of streams resumed
+    #[napi]
+    pub async fn resume_all(&self) -> Result<u32> {
+        let resumed = self.backend.lock().resume_all()?;
+        Ok(resumed as u32)
+    }
+
+    /// Crossfade from one playback stream to another.
+    ///
+    /// Starts the target silent (prebuffering if it has too little audio),
+    /// then ramps the source down to 0 while ramping the target up to 1.
+    ///
+    /// @param fromHandle - Stream to fade out
+    /// @param toHandle - Stream to fade in
+    /// @param durationMs - Fade length in milliseconds
+    #[napi]
+    pub async fn crossfade(
+        &self,
+        from_handle: u32,
+        to_handle: u32,
+        duration_ms: u32,
+    ) -> Result<()> {
+        let fade = Crossfade::new(
+            StreamHandle::new(from_handle),
+            StreamHandle::new(to_handle),
+            Duration::from_millis(duration_ms as u64),
+        );
+        let backend = Arc::clone(&self.backend);
+        run_blocking(move || fade.run(&backend, &SystemClock)).await
+    }
+
     /// Write audio samples to a playback stream.
     ///
     /// Samples should be Float32Array of interleaved samples.
@@ -369,7 +719,10 @@ impl AudioManager {
         self.backend
             .lock()
             .set_volume(StreamHandle::new(handle), volume as f32)
-            .map_err(|e| napi::Error::from(e))
+            .map_err(|e| napi::Error::from(e))?;
+        // Ducking scales from the volume the user chose
+        self.ducking.set_base_volume(StreamHandle::new(handle), volume as f32);
+        Ok(())
     }
 
     /// Get current stream volume.
@@ -383,6 +736,65 @@ impl AudioManager {
         Ok(volume as f64)
     }
 
+    /// Get the number of frames consumed since the stream was started.
+    #[napi]
+    pub fn get_position(&self, handle: u32) -> Result<i64> {
+        let position = self
+            .backend
+            .lock()
+            .get_position(StreamHandle::new(handle))
+            .map_err(napi::Error::from)?;
+        Ok(position as i64)
+    }
+
+    /// Select the ducking strategy: "simple", "fade", or "proportional".
+    #[napi]
+    pub fn set_ducking_strategy(&mut self, name: String) -> Result<()> {
+        let strategy = ducking::strategy_by_name(&name).ok_or_else(|| {
+            napi::Error::new(
+                napi::Status::InvalidArg,
+                format!("Unknown ducking strategy: {}", name),
+            )
+        })?;
+        self.ducking.set_strategy(strategy);
+        Ok(())
+    }
+
+    /// Set a stream's ducking priority (0-100, higher = more important).
+    #[napi]
+    pub fn set_stream_priority(&self, handle: u32, priority: u32) {
+        self.priorities.lock().insert(handle, priority.min(100) as u8);
+    }
+
+    /// Recalculate ducking for all prioritized streams and apply the volumes.
+    #[napi]
+    pub fn update_ducking(&self) -> Result<()> {
+        let streams: Vec<StreamInfo> = {
+            let backend = self.backend.lock();
+            let mut priorities = self.priorities.lock();
+            // Forget streams the backend no longer knows about
+            priorities.retain(|&handle, _| backend.get_volume(StreamHandle::new(handle)).is_ok());
+            priorities
+                .iter()
+                .map(|(&handle, &priority)| {
+                    let handle = StreamHandle::new(handle);
+                    let volume = backend.get_volume(handle).unwrap_or(1.0);
+                    StreamInfo {
+                        handle,
+                        priority,
+                        current_volume: volume,
+                        target_volume: volume,
+                    }
+                })
+                .collect()
+        };
+
+        self.ducking
+            .apply(&streams)
+            .map(|_| ())
+            .map_err(napi::Error::from)
+    }
+
     /// Get buffer health metrics for a stream.
     #[napi]
     pub fn get_health(&self, handle: u32) -> Result<JsHealthMetrics> {
@@ -395,12 +807,18 @@ impl AudioManager {
     }
 
     /// Wait for a playback stream to drain all queued audio.
+    ///
+    /// Other calls on this manager keep working while the drain waits.
+    ///
+    /// @param handle - Stream handle
+    /// @param timeoutMs - Give up after this long (default 5000)
+    /// @returns True if the stream drained, false on timeout
     #[napi]
-    pub async fn drain(&self, handle: u32) -> Result<()> {
-        self.backend
-            .lock()
-            .drain(StreamHandle::new(handle))
-            .map_err(|e| napi::Error::from(e))
+    pub async fn drain(&self, handle: u32, timeout_ms: Option<u32>) -> Result<bool> {
+        let timeout = timeout_ms.map_or(DRAIN_TIMEOUT, |ms| Duration::from_millis(ms as u64));
+        let backend = Arc::clone(&self.backend);
+        run_blocking(move || backend::drain::drain(&backend, StreamHandle::new(handle), timeout))
+            .await
     }
 
     /// List available playback devices.
@@ -453,3 +871,40 @@ impl Default for AudioManager {
         Self::new()
     }
 }
+
+impl AudioManager {
+    /// Switch to `backend`, carrying over the manager-wide settings and
+    /// forgetting per-stream state from the previous backend.
+    pub(crate) fn install_backend(&mut self, mut backend: Box<dyn Backend>) {
+        backend.set_default_device_listener(self.device_listener.lock().clone());
+        backend.set_max_streams(*self.max_streams.lock());
+        self.backend = Arc::new(Mutex::new(backend));
+        self.ducking.set_backend(self.backend.clone());
+        self.priorities.lock().clear();
+        self.names.clear();
+        self.initialized = true;
+    }
+
+    /// Route default device changes to `listener`, now and after any later
+    /// `initialize`.
+    pub(crate) fn subscribe_default_device(&self, listener: DefaultDeviceListener) {
+        *self.device_listener.lock() = Some(listener.clone());
+        self.backend.lock().set_default_device_listener(Some(listener));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unknown_backend_error_keeps_code() {
+        let Err(err) = backend::create_backend("alsa", false) else {
+            panic!("unknown backend accepted");
+        };
+        let err = backend_creation_error(err);
+
+        assert_eq!(err.status, napi::Status::InvalidArg);
+        assert!(err.reason.starts_with("ERR_INVALID_CONFIG: "), "{}", err.reason);
+    }
+}
@@ -0,0 +1,124 @@
+//! Timed volume hand-over between two playback streams.
+
+use std::time::Duration;
+use parking_lot::Mutex;
+
+use crate::backend::clock::Clock;
+use crate::backend::{Backend, Result, StreamHandle, StreamState};
+
+/// How often volumes are updated during a crossfade.
+const STEP: Duration = Duration::from_millis(10);
+
+/// Linear crossfade from one stream to another.
+///
+/// The source ramps from 1.0 to 0.0 while the target ramps from 0.0 to 1.0,
+/// so the two volumes always sum to 1.0.
+pub struct Crossfade {
+    from: StreamHandle,
+    to: StreamHandle,
+    duration: Duration,
+}
+
+impl Crossfade {
+    pub fn new(from: StreamHandle, to: StreamHandle, duration: Duration) -> Self {
+        Self { from, to, duration }
+    }
+
+    /// Source and target volumes `elapsed` into the fade.
+    pub fn volumes_at(&self, elapsed: Duration) -> (f32, f32) {
+        let progress = if self.duration.is_zero() {
+            1.0
+        } else {
+            (elapsed.as_secs_f32() / self.duration.as_secs_f32()).min(1.0)
+        };
+        (1.0 - progress, progress)
+    }
+
+    /// Silence the target and start it if it is not already playing.
+    ///
+    /// A target without enough buffered audio starts in prebuffering.
+    pub fn begin(&self, backend: &Mutex<Box<dyn Backend>>) -> Result<()> {
+        let mut backend = backend.lock();
+        backend.set_volume(self.to, 0.0)?;
+        if matches!(
+            backend.get_state(self.to)?,
+            StreamState::Idle | StreamState::Paused
+        ) {
+            backend.start(self.to)?;
+        }
+        Ok(())
+    }
+
+    /// Apply the volumes for `elapsed` into the fade.
+    pub fn apply(&self, backend: &Mutex<Box<dyn Backend>>, elapsed: Duration) -> Result<()> {
+        let (from_volume, to_volume) = self.volumes_at(elapsed);
+        let mut backend = backend.lock();
+        backend.set_volume(self.from, from_volume)?;
+        backend.set_volume(self.to, to_volume)
+    }
+
+    /// Run the whole fade, blocking on `clock` between volume updates.
+    ///
+    /// The backend lock is released while waiting.
+    pub fn run(&self, backend: &Mutex<Box<dyn Backend>>, clock: &dyn Clock) -> Result<()> {
+        self.begin(backend)?;
+
+        let start = clock.now();
+        loop {
+            let elapsed = clock.now().duration_since(start);
+            self.apply(backend, elapsed)?;
+            if elapsed >= self.duration {
+                return Ok(());
+            }
+            clock.sleep(STEP.min(self.duration - elapsed));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::clock::MockClock;
+    use crate::backend::mock::MockBackend;
+    use crate::backend::StreamConfig;
+
+    fn backend_with_streams() -> (Mutex<Box<dyn Backend>>, StreamHandle, StreamHandle) {
+        let mut backend = MockBackend::new();
+        backend.initialize().unwrap();
+        let from = backend.create_stream(StreamConfig::default()).unwrap();
+        let to = backend.create_stream(StreamConfig::default()).unwrap();
+        backend.start(from).unwrap();
+        (Mutex::new(Box::new(backend)), from, to)
+    }
+
+    #[test]
+    fn test_volumes_sum_to_one_midway() {
+        let (backend, from, to) = backend_with_streams();
+        let fade = Crossfade::new(from, to, Duration::from_millis(200));
+
+        fade.begin(&backend).unwrap();
+        fade.apply(&backend, Duration::from_millis(100)).unwrap();
+
+        let backend = backend.lock();
+        let from_volume = backend.get_volume(from).unwrap();
+        let to_volume = backend.get_volume(to).unwrap();
+        assert!((from_volume - 0.5).abs() < 0.01);
+        assert!((from_volume + to_volume - 1.0).abs() < 0.01);
+        // Target was started, waiting on its prebuffer
+        assert_eq!(backend.get_state(to).unwrap(), StreamState::Prebuffering);
+    }
+
+    #[test]
+    fn test_run_ends_at_full_hand_over() {
+        let (backend, from, to) = backend_with_streams();
+        let clock = MockClock::new();
+        let fade = Crossfade::new(from, to, Duration::from_millis(250));
+
+        fade.run(&backend, &clock).unwrap();
+
+        assert_eq!(clock.elapsed(), Duration::from_millis(250));
+        let backend = backend.lock();
+        assert_eq!(backend.get_volume(from).unwrap(), 0.0);
+        assert_eq!(backend.get_volume(to).unwrap(), 1.0);
+    }
+}
@@ -0,0 +1,129 @@
+//! Output mixing stages.
+//!
+//! Processing applied to the summed output of several streams before it
+//! reaches the device, volume hand-overs between streams, and capture of
+//! the mixed output.
+
+pub mod crossfade;
+pub mod recorder;
+
+pub use crossfade::Crossfade;
+pub use recorder::{Recorder, RecorderSlot, RecorderTap};
+
+/// Output ceiling the limiter never exceeds.
+const CEILING: f32 = 1.0;
+
+/// Soft-knee peak limiter for the master bus.
+///
+/// Levels below `threshold` pass through untouched. Above it, peaks are
+/// compressed smoothly towards the ceiling instead of being hard-clipped.
+/// Gain drops instantly on a peak and recovers over the release time.
+pub struct Limiter {
+    /// Level (linear, 0.0 - 1.0) where the knee starts
+    threshold: f32,
+    /// Per-sample gain recovery coefficient
+    release_coeff: f32,
+    /// Current gain applied to the signal
+    gain: f32,
+}
+
+impl Limiter {
+    /// Create a limiter with the knee at `threshold` and the given release time.
+    pub fn new(threshold: f32, release_ms: f32, sample_rate: u32) -> Self {
+        let release_samples = (release_ms.max(0.0) / 1000.0) * sample_rate as f32;
+        let release_coeff = if release_samples > 0.0 {
+            1.0 - (-1.0 / release_samples).exp()
+        } else {
+            1.0
+        };
+
+        Self {
+            threshold: threshold.clamp(0.0, CEILING),
+            release_coeff,
+            gain: 1.0,
+        }
+    }
+
+    /// Limit interleaved samples in place.
+    pub fn process(&mut self, samples: &mut [f32]) {
+        for sample in samples.iter_mut() {
+            let target = self.target_gain(sample.abs());
+
+            if target < self.gain {
+                // Instant attack so no peak gets past the ceiling
+                self.gain = target;
+            } else {
+                self.gain += (target - self.gain) * self.release_coeff;
+            }
+
+            *sample *= self.gain;
+        }
+    }
+
+    /// Gain that maps `level` onto the soft-knee curve.
+    fn target_gain(&self, level: f32) -> f32 {
+        if level <= self.threshold {
+            return 1.0;
+        }
+
+        let headroom = CEILING - self.threshold;
+        if headroom <= 0.0 {
+            return CEILING / level;
+        }
+
+        // Approaches the ceiling asymptotically above the threshold
+        let limited = self.threshold + headroom * ((level - self.threshold) / headroom).tanh();
+        limited / level
+    }
+}
+
+impl Default for Limiter {
+    fn default() -> Self {
+        Self::new(0.9, 50.0, 48000)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(amplitude: f32, len: usize) -> Vec<f32> {
+        (0..len)
+            .map(|i| amplitude * (i as f32 * 2.0 * std::f32::consts::PI * 440.0 / 48000.0).sin())
+            .collect()
+    }
+
+    #[test]
+    fn test_limits_peaks_below_ceiling() {
+        let mut limiter = Limiter::default();
+        let mut samples = sine(1.5, 4800);
+
+        limiter.process(&mut samples);
+
+        assert!(samples.iter().all(|s| s.abs() < CEILING));
+    }
+
+    #[test]
+    fn test_passes_signal_below_threshold() {
+        let mut limiter = Limiter::default();
+        let original = sine(0.5, 4800);
+        let mut samples = original.clone();
+
+        limiter.process(&mut samples);
+
+        assert_eq!(samples, original);
+    }
+
+    #[test]
+    fn test_gain_recovers_after_peak() {
+        let mut limiter = Limiter::new(0.9, 10.0, 48000);
+        let mut peak = vec![2.0];
+        limiter.process(&mut peak);
+
+        // 100ms of quiet signal is ten release time constants
+        let mut quiet = vec![0.1; 4800];
+        limiter.process(&mut quiet);
+
+        assert!((quiet[4799] - 0.1).abs() < 0.001);
+    }
+}
@@ -0,0 +1,381 @@
+//! Capture of the mixed playback output.
+//!
+//! Backends hand every rendered playback block to the attached `Recorder`,
+//! which sums the streams into a single timeline and encodes it as WAV
+//! through the master `Limiter`.
+//! Real-time callbacks go through a `RecorderQueue` instead, so they never
+//! lock or allocate.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use parking_lot::Mutex;
+
+use crate::backend::{AudioFormat, StreamConfig, StreamHandle};
+use crate::buffer::RingBuffer;
+use crate::mix::Limiter;
+
+/// Size of a canonical PCM WAV header.
+const WAV_HEADER_LEN: usize = 44;
+
+/// Longest recording kept; later audio is dropped.
+pub const MAX_RECORDING_SECS: u32 = 600;
+
+/// Audio a `RecorderQueue` holds between flushes.
+const QUEUE_MS: u32 = 500;
+
+/// Master limiter knee for the mixed output.
+const LIMITER_THRESHOLD: f32 = 0.9;
+
+/// Master limiter release time in milliseconds.
+const LIMITER_RELEASE_MS: f32 = 50.0;
+
+/// Accumulates rendered playback blocks into one mixed signal.
+///
+/// Each stream writes at its own cursor, so blocks from different streams
+/// rendered in the same device cycle overlap and are summed. Backends hand
+/// over a block every cycle, silent while a stream is not playing, so the
+/// cursors advance with the device. Streams that existed when recording
+/// started begin at frame 0; later streams begin at the current end of the
+/// recording.
+pub struct Recorder {
+    sample_rate: u32,
+    channels: u32,
+    /// Cap on recorded samples
+    max_samples: usize,
+    state: Mutex<RecorderState>,
+}
+
+struct RecorderState {
+    /// Interleaved mixed samples
+    samples: Vec<f32>,
+    /// Next sample index per stream
+    cursors: HashMap<StreamHandle, usize>,
+}
+
+impl Recorder {
+    /// Create a recorder for the given output format, aligning `sources`
+    /// at the start of the recording.
+    pub fn new(sample_rate: u32, channels: u32, sources: &[StreamHandle]) -> Self {
+        let channels = channels.max(1);
+        Self {
+            sample_rate,
+            channels,
+            max_samples: sample_rate as usize * channels as usize * MAX_RECORDING_SECS as usize,
+            state: Mutex::new(RecorderState {
+                samples: Vec::new(),
+                cursors: sources.iter().map(|&handle| (handle, 0)).collect(),
+            }),
+        }
+    }
+
+    /// Keep at most `frames` frames instead of `MAX_RECORDING_SECS`.
+    pub fn with_max_frames(mut self, frames: usize) -> Self {
+        self.max_samples = frames * self.channels as usize;
+        self
+    }
+
+    /// Mix a rendered block from `source` into the recording.
+    ///
+    /// Audio past the length cap is dropped.
+    pub fn add(&self, source: StreamHandle, samples: &[f32]) {
+        let mut state = self.state.lock();
+        let end = state.samples.len();
+        let cursor = *state.cursors.entry(source).or_insert(end);
+        if cursor >= self.max_samples {
+            return;
+        }
+        let samples = &samples[..samples.len().min(self.max_samples - cursor)];
+
+        let needed = cursor + samples.len();
+        if needed > state.samples.len() {
+            state.samples.resize(needed, 0.0);
+        }
+        for (mixed, sample) in state.samples[cursor..needed].iter_mut().zip(samples) {
+            *mixed += sample;
+        }
+        state.cursors.insert(source, needed);
+    }
+
+    /// Number of recorded frames.
+    pub fn frames(&self) -> usize {
+        self.state.lock().samples.len() / self.channels as usize
+    }
+
+    /// Encode the recording as a WAV file in `format`.
+    ///
+    /// The mix goes through a master `Limiter`, so summed streams stay
+    /// within full scale instead of clipping.
+    pub fn to_wav(&self, format: AudioFormat) -> Vec<u8> {
+        let mut samples = self.state.lock().samples.clone();
+        Limiter::new(LIMITER_THRESHOLD, LIMITER_RELEASE_MS, self.sample_rate).process(&mut samples);
+
+        let bytes_per_sample = format.bytes_per_sample();
+        let data_len = samples.len() * bytes_per_sample;
+        let block_align = self.channels as usize * bytes_per_sample;
+        // WAVE_FORMAT_IEEE_FLOAT for f32, WAVE_FORMAT_PCM otherwise
+        let format_tag: u16 = match format {
+            AudioFormat::F32LE => 3,
+            AudioFormat::S16LE | AudioFormat::S32LE => 1,
+        };
+
+        let mut wav = Vec::with_capacity(WAV_HEADER_LEN + data_len);
+        wav.extend_from_slice(b"RIFF");
+        wav.extend_from_slice(&((WAV_HEADER_LEN - 8 + data_len) as u32).to_le_bytes());
+        wav.extend_from_slice(b"WAVE");
+        wav.extend_from_slice(b"fmt ");
+        wav.extend_from_slice(&16u32.to_le_bytes());
+        wav.extend_from_slice(&format_tag.to_le_bytes());
+        wav.extend_from_slice(&(self.channels as u16).to_le_bytes());
+        wav.extend_from_slice(&self.sample_rate.to_le_bytes());
+        wav.extend_from_slice(&((self.sample_rate as usize * block_align) as u32).to_le_bytes());
+        wav.extend_from_slice(&(block_align as u16).to_le_bytes());
+        wav.extend_from_slice(&((bytes_per_sample * 8) as u16).to_le_bytes());
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(data_len as u32).to_le_bytes());
+
+        for &sample in &samples {
+            match format {
+                AudioFormat::F32LE => wav.extend_from_slice(&sample.to_le_bytes()),
+                AudioFormat::S16LE => {
+                    let value = (sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16;
+                    wav.extend_from_slice(&value.to_le_bytes());
+                }
+                AudioFormat::S32LE => {
+                    let value = (sample.clamp(-1.0, 1.0) as f64 * i32::MAX as f64) as i32;
+                    wav.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+
+        wav
+    }
+}
+
+/// Backend-wide attachment point for a `Recorder`.
+#[derive(Clone, Default)]
+pub struct RecorderSlot {
+    recorder: Arc<Mutex<Option<Arc<Recorder>>>>,
+    /// Whether a recorder is attached, readable without the lock
+    recording: Arc<AtomicBool>,
+}
+
+impl RecorderSlot {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Start capturing into `recorder`, replacing any previous one.
+    pub fn attach(&self, recorder: Recorder) {
+        *self.recorder.lock() = Some(Arc::new(recorder));
+        self.recording.store(true, Ordering::Release);
+    }
+
+    /// Stop capturing, returning the recorder if one was attached.
+    pub fn detach(&self) -> Option<Arc<Recorder>> {
+        self.recording.store(false, Ordering::Release);
+        self.recorder.lock().take()
+    }
+
+    /// Whether a recorder is attached.
+    pub fn is_recording(&self) -> bool {
+        self.recording.load(Ordering::Acquire)
+    }
+
+    /// Capture point for one stream.
+    pub fn tap(&self, source: StreamHandle, config: &StreamConfig) -> RecorderTap {
+        RecorderTap {
+            slot: self.clone(),
+            source,
+            sample_rate: config.sample_rate,
+            channels: config.channels,
+        }
+    }
+}
+
+/// A stream's handle on the backend's `RecorderSlot`.
+#[derive(Clone)]
+pub struct RecorderTap {
+    slot: RecorderSlot,
+    source: StreamHandle,
+    sample_rate: u32,
+    channels: u32,
+}
+
+impl RecorderTap {
+    /// Hand a rendered block (after volume) to the attached recorder.
+    ///
+    /// Streams whose format differs from the recording are skipped.
+    pub fn capture(&self, samples: &[f32]) {
+        let recorder = self.slot.recorder.lock().clone();
+        if let Some(recorder) = recorder {
+            if recorder.sample_rate == self.sample_rate && recorder.channels == self.channels {
+                recorder.add(self.source, samples);
+            }
+        }
+    }
+}
+
+/// Lock-free hand-off from a real-time callback to a stream's `RecorderTap`.
+///
+/// The audio thread pushes rendered blocks into a preallocated ring; a
+/// non-real-time thread moves them into the recorder with `flush`. One
+/// thread may push and one may flush.
+pub struct RecorderQueue {
+    tap: RecorderTap,
+    pending: RingBuffer,
+}
+
+impl RecorderQueue {
+    /// Queue for `tap`, holding up to `QUEUE_MS` of audio in the stream's format.
+    pub fn new(tap: RecorderTap) -> Self {
+        let pending = RingBuffer::for_duration(tap.sample_rate, tap.channels, QUEUE_MS);
+        Self { tap, pending }
+    }
+
+    /// Queue a rendered block if a recording is in progress.
+    ///
+    /// Never locks or allocates. Audio that does not fit is dropped.
+    pub fn push(&self, samples: &[f32]) {
+        if self.tap.slot.is_recording() {
+            self.pending.write(samples);
+        }
+    }
+
+    /// Move queued audio into the attached recorder.
+    pub fn flush(&self) {
+        let mut samples = vec![0.0; self.pending.available_read()];
+        let read = self.pending.read(&mut samples);
+        if read > 0 {
+            self.tap.capture(&samples[..read]);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payload(wav: &[u8]) -> Vec<f32> {
+        wav[WAV_HEADER_LEN..]
+            .chunks_exact(4)
+            .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+            .collect()
+    }
+
+    #[test]
+    fn test_wav_payload_matches_mixed_frames() {
+        let a = StreamHandle::new(1);
+        let b = StreamHandle::new(2);
+        let recorder = Recorder::new(48000, 1, &[a, b]);
+
+        // Interleaved device cycles from two streams
+        recorder.add(a, &[0.1, 0.2, 0.3]);
+        recorder.add(b, &[0.05, 0.05, 0.05]);
+        recorder.add(a, &[0.4]);
+        recorder.add(b, &[-0.4]);
+
+        let wav = recorder.to_wav(AudioFormat::F32LE);
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        assert_eq!(u16::from_le_bytes([wav[20], wav[21]]), 3);
+        assert_eq!(u32::from_le_bytes([wav[24], wav[25], wav[26], wav[27]]), 48000);
+        assert_eq!(u32::from_le_bytes([wav[40], wav[41], wav[42], wav[43]]), 16);
+
+        let expected = [0.15, 0.25, 0.35, 0.0];
+        for (got, want) in payload(&wav).iter().zip(expected) {
+            assert!((got - want).abs() < 1e-6, "{got} vs {want}");
+        }
+        assert_eq!(recorder.frames(), 4);
+    }
+
+    #[test]
+    fn test_summed_streams_limited() {
+        let a = StreamHandle::new(1);
+        let b = StreamHandle::new(2);
+        let recorder = Recorder::new(48000, 1, &[a, b]);
+
+        recorder.add(a, &[0.8; 480]);
+        recorder.add(b, &[0.8; 480]);
+
+        let mixed = payload(&recorder.to_wav(AudioFormat::F32LE));
+        assert_eq!(mixed.len(), 480);
+        assert!(mixed.iter().all(|s| s.abs() <= 1.0 && *s > 0.9));
+    }
+
+    #[test]
+    fn test_late_stream_starts_at_end() {
+        let a = StreamHandle::new(1);
+        let recorder = Recorder::new(48000, 1, &[a]);
+
+        recorder.add(a, &[0.5, 0.5]);
+        recorder.add(StreamHandle::new(2), &[0.25]);
+
+        assert_eq!(payload(&recorder.to_wav(AudioFormat::F32LE)), vec![0.5, 0.5, 0.25]);
+    }
+
+    #[test]
+    fn test_tap_skips_mismatched_format() {
+        let slot = RecorderSlot::new();
+        let stereo = slot.tap(StreamHandle::new(1), &StreamConfig {
+            channels: 2,
+            ..Default::default()
+        });
+        let mono = slot.tap(StreamHandle::new(2), &StreamConfig::default());
+
+        // Nothing attached yet
+        mono.capture(&[0.1]);
+
+        slot.attach(Recorder::new(48000, 1, &[]));
+        stereo.capture(&[0.1, 0.1]);
+        mono.capture(&[0.2, 0.3]);
+
+        let recorder = slot.detach().unwrap();
+        assert_eq!(recorder.frames(), 2);
+        assert!(slot.detach().is_none());
+    }
+
+    #[test]
+    fn test_recording_capped() {
+        let a = StreamHandle::new(1);
+        let recorder = Recorder::new(48000, 2, &[a]).with_max_frames(2);
+
+        recorder.add(a, &[0.1; 6]);
+        recorder.add(a, &[0.2; 2]);
+
+        assert_eq!(recorder.frames(), 2);
+    }
+
+    #[test]
+    fn test_queue_hands_off_on_flush() {
+        let slot = RecorderSlot::new();
+        let queue = RecorderQueue::new(slot.tap(StreamHandle::new(1), &StreamConfig::default()));
+
+        // Not recording: nothing is queued
+        queue.push(&[0.1, 0.1]);
+        slot.attach(Recorder::new(48000, 1, &[]));
+        queue.flush();
+        assert_eq!(slot.recorder.lock().as_ref().unwrap().frames(), 0);
+
+        queue.push(&[0.2, 0.3]);
+        queue.push(&[0.4]);
+        assert_eq!(slot.recorder.lock().as_ref().unwrap().frames(), 0);
+
+        queue.flush();
+        let recorder = slot.detach().unwrap();
+        assert_eq!(payload(&recorder.to_wav(AudioFormat::F32LE)), vec![0.2, 0.3, 0.4]);
+    }
+
+    #[test]
+    fn test_queue_drops_overflow() {
+        let slot = RecorderSlot::new();
+        let queue = RecorderQueue::new(slot.tap(StreamHandle::new(1), &StreamConfig::default()));
+        slot.attach(Recorder::new(48000, 1, &[]));
+
+        let capacity = queue.pending.capacity();
+        queue.push(&vec![0.5; capacity + 100]);
+        queue.flush();
+
+        assert_eq!(slot.detach().unwrap().frames(), capacity);
+    }
+}
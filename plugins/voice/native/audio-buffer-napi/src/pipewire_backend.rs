@@ -3,29 +3,190 @@
 //! This module provides native PipeWire integration for low-latency audio.
 //! It creates pw_stream instances for playback and recording, and uses
 //! lock-free ring buffers to communicate with the audio thread.
+//!
+//! PipeWire objects are not `Send`, so all of them (main loop, context,
+//! core and streams) live on a dedicated main-loop thread. The backend
+//! talks to that thread through a `pw::channel` of [`PwCommand`]s.
+//!
+//! Streams connect without a target node, so the session manager moves
+//! them when the user switches the default device. The backend only watches
+//! the "default" metadata object to report those switches.
 
+use std::cell::RefCell;
 use std::collections::HashMap;
-use std::sync::{Arc, atomic::{AtomicBool, Ordering}};
+use std::io::Cursor;
+use std::rc::Rc;
+use std::sync::{mpsc, Arc, atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering}};
 use std::thread::{self, JoinHandle};
+use std::time::Duration;
 use parking_lot::{Mutex, RwLock};
 
 use pipewire as pw;
 use pw::prelude::*;
+use pw::spa;
 
 use crate::backend::{
-    AudioDevice, Backend, BackendError, Result, StreamConfig, StreamDirection,
-    StreamHandle, StreamState, AudioFormat,
+    check_stream_limit, AudioDevice, Backend, BackendError, Capabilities, DefaultDeviceListener,
+    Result, StreamConfig, StreamDirection, StreamHandle, StreamState, AudioFormat, UnderrunPolicy,
+    DEFAULT_MAX_STREAMS,
 };
-use crate::buffer::{HealthMetrics, HealthMonitor, RingBuffer};
+use crate::backend::clock::{Clock, SystemClock};
+use crate::backend::dsp::{Biquad, NoiseGate, Resampler};
+use crate::buffer::{BackpressureListener, HealthMetrics, HealthMonitor, RingBuffer};
+use crate::mix::{Recorder, RecorderSlot};
+use crate::mix::recorder::RecorderQueue;
+
+/// How long to wait for the main-loop thread to answer a command.
+const COMMAND_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often the main loop moves queued playback audio into the recorder.
+const RECORDING_FLUSH: Duration = Duration::from_millis(20);
+
+/// Largest quantum (frames per process cycle) the callbacks convert at once.
+///
+/// Scratch space is allocated for this up front so the RT thread never
+/// allocates; larger buffers are filled up to this many frames.
+const MAX_QUANTUM_FRAMES: usize = 8192;
 
 /// PipeWire stream wrapper.
 struct PwStreamWrapper {
     config: StreamConfig,
     buffer: Arc<RingBuffer>,
     health: Arc<HealthMonitor>,
-    volume: f32,
-    state: StreamState,
-    // Stream lifecycle managed by PipeWire context
+    /// Volume as `f32` bits, shared with the process callback
+    volume: Arc<AtomicU32>,
+    /// Frames consumed since the stream was started
+    position: Arc<AtomicU64>,
+    /// Read-path noise gate (recording streams only)
+    gate: Option<Mutex<NoiseGate>>,
+    /// Write-path EQ filter (playback streams only)
+    filter: Option<Mutex<Biquad>>,
+    /// Write-path rate converter (playback streams only)
+    resampler: Option<Mutex<Resampler>>,
+    /// Tees receiving a copy of every write
+    tees: Vec<StreamHandle>,
+    // Stream lifecycle managed by PipeWire context; state lives in `health`
+}
+
+impl PwStreamWrapper {
+    fn state(&self) -> StreamState {
+        self.health.get_state()
+    }
+
+    /// Re-check the prebuffer water marks after the fill level changed.
+    fn update_water_marks(&self) {
+        let capacity = self.buffer.capacity();
+        self.health.apply_water_marks(
+            self.buffer.available_read(),
+            self.config.low_water_samples(capacity),
+            self.config.high_water_samples(capacity),
+        );
+    }
+}
+
+/// Commands sent from the backend to the main-loop thread.
+enum PwCommand {
+    /// Create and connect a pw_stream backed by the given ring buffer.
+    CreateStream {
+        handle: StreamHandle,
+        config: StreamConfig,
+        buffer: Arc<RingBuffer>,
+        health: Arc<HealthMonitor>,
+        volume: Arc<AtomicU32>,
+        position: Arc<AtomicU64>,
+        recording: Arc<RecorderQueue>,
+        reply: mpsc::Sender<Result<()>>,
+    },
+    /// Disconnect and drop a pw_stream.
+    DestroyStream(StreamHandle),
+    /// Move all queued playback audio into the recorder, then reply.
+    FlushRecording(mpsc::Sender<()>),
+    /// Drop all streams and quit the main loop.
+    Terminate,
+}
+
+/// State owned by a stream's process callback (runs on the audio thread).
+struct ProcessState {
+    buffer: Arc<RingBuffer>,
+    health: Arc<HealthMonitor>,
+    volume: Arc<AtomicU32>,
+    position: Arc<AtomicU64>,
+    /// Lock-free hand-off to the mix-down recorder
+    recording: Arc<RecorderQueue>,
+    format: AudioFormat,
+    channels: usize,
+    direction: StreamDirection,
+    /// Prebuffer water marks in samples
+    low_water: usize,
+    high_water: usize,
+    /// Recovery after a playback underrun
+    underrun_policy: UnderrunPolicy,
+    /// Scratch space for f32 <-> wire format conversion, sized for
+    /// [`MAX_QUANTUM_FRAMES`]
+    scratch: Vec<f32>,
+}
+
+/// A connected pw_stream and its listener, owned by the main-loop thread.
+struct ActiveStream {
+    // Declared first so the listener is dropped before the stream
+    _listener: pw::stream::StreamListener<ProcessState>,
+    stream: pw::stream::Stream,
+    /// Drained into the recorder from the main loop
+    recording: Arc<RecorderQueue>,
+    /// Backpressure crossings seen by the process callback are reported
+    /// from the main loop
+    health: Arc<HealthMonitor>,
+}
+
+/// Object id of the core, the subject of default device metadata.
+const PW_ID_CORE: u32 = 0;
+
+/// Tracks the default sink and source announced in the session manager's
+/// "default" metadata and notifies a listener when either changes.
+#[derive(Default)]
+struct DefaultDeviceTracker {
+    listener: Mutex<Option<DefaultDeviceListener>>,
+    /// Last seen default node names
+    sink: Mutex<Option<String>>,
+    source: Mutex<Option<String>>,
+}
+
+impl DefaultDeviceTracker {
+    /// Handle a property update on the "default" metadata object.
+    ///
+    /// The first value seen for each direction is the starting default and
+    /// is recorded without notifying.
+    fn on_property(&self, subject: u32, key: Option<&str>, value: Option<&str>) {
+        if subject != PW_ID_CORE {
+            return;
+        }
+        let (direction, current) = match key {
+            Some("default.audio.sink") => (StreamDirection::Playback, &self.sink),
+            Some("default.audio.source") => (StreamDirection::Recording, &self.source),
+            _ => return,
+        };
+        let Some(name) = value.and_then(parse_metadata_name) else {
+            return;
+        };
+
+        let previous = current.lock().replace(name.to_string());
+        if previous.is_none() || previous.as_deref() == Some(name) {
+            return;
+        }
+
+        // Clone out so the callback runs without the lock held
+        let listener = self.listener.lock().clone();
+        if let Some(listener) = listener {
+            listener(direction, name);
+        }
+    }
+}
+
+/// Extract the node name from a metadata value like `{ "name": "alsa_output.pci" }`.
+fn parse_metadata_name(value: &str) -> Option<&str> {
+    let rest = &value[value.find("\"name\"")? + "\"name\"".len()..];
+    let rest = rest.trim_start().strip_prefix(':')?.trim_start().strip_prefix('"')?;
+    rest.split('"').next().filter(|name| !name.is_empty())
 }
 
 /// PipeWire backend for native audio.
@@ -40,11 +201,35 @@ pub struct PipeWireBackend {
     running: Arc<AtomicBool>,
     /// Main loop thread handle
     main_loop_thread: Option<JoinHandle<()>>,
+    /// Command channel into the main loop thread
+    commands: Option<Mutex<pw::channel::Sender<PwCommand>>>,
+    /// Time source for drain polling
+    clock: Arc<dyn Clock>,
+    /// Default device state, updated from the main loop thread
+    defaults: Arc<DefaultDeviceTracker>,
+    /// Mix-down capture shared with every stream's process callback
+    recorder: RecorderSlot,
+    /// Cap on open streams, tees included
+    max_streams: usize,
 }
 
 impl PipeWireBackend {
+    /// Real device I/O through the PipeWire server. Volume is still scaled
+    /// in the process callback, and only the default sink/source are listed.
+    pub const CAPABILITIES: Capabilities = Capabilities {
+        hardware_io: true,
+        hardware_volume: false,
+        device_enumeration: false,
+        recording: true,
+    };
+
     /// Create a new PipeWire backend.
     pub fn new() -> Result<Self> {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Create a PipeWire backend that waits on the given clock.
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Result<Self> {
         // Initialize PipeWire library
         pw::init();
 
@@ -54,15 +239,20 @@ impl PipeWireBackend {
             initialized: false,
             running: Arc::new(AtomicBool::new(false)),
             main_loop_thread: None,
+            commands: None,
+            clock,
+            defaults: Arc::new(DefaultDeviceTracker::default()),
+            recorder: RecorderSlot::new(),
+            max_streams: DEFAULT_MAX_STREAMS,
         })
     }
 
     /// Get sample format for PipeWire.
-    fn get_pw_format(format: AudioFormat) -> &'static str {
+    fn get_pw_format(format: AudioFormat) -> spa::param::audio::AudioFormat {
         match format {
-            AudioFormat::F32LE => "F32LE",
-            AudioFormat::S16LE => "S16LE",
-            AudioFormat::S32LE => "S32LE",
+            AudioFormat::F32LE => spa::param::audio::AudioFormat::F32LE,
+            AudioFormat::S16LE => spa::param::audio::AudioFormat::S16LE,
+            AudioFormat::S32LE => spa::param::audio::AudioFormat::S32LE,
         }
     }
 
@@ -77,6 +267,429 @@ impl PipeWireBackend {
             .get_mut(&handle)
             .ok_or(BackendError::StreamNotFound(handle))
     }
+
+    /// Copy samples just buffered by a playback stream into its tees.
+    fn mirror(&self, tees: &[StreamHandle], samples: &[f32]) {
+        for tee in tees.iter().filter_map(|handle| self.streams.get(handle)) {
+            let written = tee.buffer.write(samples);
+            if written < samples.len() {
+                tee.health.record_overrun();
+            }
+            tee.health.set_fill_level(tee.buffer.fill_percent());
+            tee.health.deliver_backpressure();
+        }
+    }
+
+    /// Send a command to the main loop thread.
+    fn send_command(&self, command: PwCommand) -> Result<()> {
+        let commands = self
+            .commands
+            .as_ref()
+            .ok_or_else(|| BackendError::NotAvailable("PipeWire main loop not running".into()))?;
+        commands
+            .lock()
+            .send(command)
+            .map_err(|_| BackendError::Internal("PipeWire main loop has exited".into()))
+    }
+}
+
+/// Body of the main loop thread.
+///
+/// Reports readiness (or the connection error) through `ready`, then runs
+/// the loop until a `Terminate` command arrives.
+fn run_main_loop(
+    commands: pw::channel::Receiver<PwCommand>,
+    ready: mpsc::Sender<Result<()>>,
+    defaults: Arc<DefaultDeviceTracker>,
+) {
+    let setup = || -> Result<(pw::main_loop::MainLoop, pw::context::Context, pw::core::Core)> {
+        let main_loop = pw::main_loop::MainLoop::new(None)
+            .map_err(|e| BackendError::ConnectionFailed(format!("main loop: {}", e)))?;
+        let context = pw::context::Context::new(&main_loop)
+            .map_err(|e| BackendError::ConnectionFailed(format!("context: {}", e)))?;
+        let core = context
+            .connect(None)
+            .map_err(|e| BackendError::ConnectionFailed(format!("core: {}", e)))?;
+        Ok((main_loop, context, core))
+    };
+
+    let (main_loop, _context, core) = match setup() {
+        Ok(objects) => objects,
+        Err(e) => {
+            let _ = ready.send(Err(e));
+            return;
+        }
+    };
+
+    // Default device tracking is best effort; streams work without it
+    let _default_watch = watch_default_devices(&core, defaults)
+        .map_err(|e| tracing::warn!("PipeWire default device tracking unavailable: {}", e))
+        .ok();
+
+    let active: Rc<RefCell<HashMap<StreamHandle, ActiveStream>>> = Rc::default();
+    let flush_all = {
+        let active = Rc::clone(&active);
+        move || {
+            for stream in active.borrow().values() {
+                stream.recording.flush();
+                stream.health.deliver_backpressure();
+            }
+        }
+    };
+
+    // Recording copies and backpressure listeners run here rather than in
+    // the process callback
+    let flush_timer = main_loop.loop_().add_timer({
+        let flush_all = flush_all.clone();
+        move |_expirations| flush_all()
+    });
+    let _ = flush_timer.update_timer(Some(RECORDING_FLUSH), Some(RECORDING_FLUSH));
+
+    let loop_handle = main_loop.clone();
+    let _receiver = commands.attach(main_loop.loop_(), move |command| match command {
+        PwCommand::CreateStream {
+            handle,
+            config,
+            buffer,
+            health,
+            volume,
+            position,
+            recording,
+            reply,
+        } => {
+            let result = connect_stream(&core, &config, buffer, health, volume, position, recording)
+                .map(|stream| {
+                    active.borrow_mut().insert(handle, stream);
+                });
+            let _ = reply.send(result);
+        }
+        PwCommand::DestroyStream(handle) => {
+            if let Some(stream) = active.borrow_mut().remove(&handle) {
+                let _ = stream.stream.disconnect();
+                stream.recording.flush();
+            }
+        }
+        PwCommand::FlushRecording(reply) => {
+            flush_all();
+            let _ = reply.send(());
+        }
+        PwCommand::Terminate => {
+            for (_, stream) in active.borrow_mut().drain() {
+                let _ = stream.stream.disconnect();
+            }
+            loop_handle.quit();
+        }
+    });
+
+    let _ = ready.send(Ok(()));
+    main_loop.run();
+}
+
+/// Registry and metadata proxies kept alive while default devices are watched.
+struct DefaultDeviceWatch {
+    // Listeners are declared before the proxies they observe
+    _registry_listener: pw::registry::Listener,
+    _metadata: Rc<RefCell<Vec<(pw::metadata::MetadataListener, pw::metadata::Metadata)>>>,
+    _registry: Rc<pw::registry::Registry>,
+}
+
+/// Bind the session manager's "default" metadata and feed its property
+/// updates to `defaults`.
+fn watch_default_devices(
+    core: &pw::core::Core,
+    defaults: Arc<DefaultDeviceTracker>,
+) -> Result<DefaultDeviceWatch> {
+    let registry = Rc::new(
+        core.get_registry()
+            .map_err(|e| BackendError::ConnectionFailed(format!("registry: {}", e)))?,
+    );
+    let metadata = Rc::new(RefCell::new(Vec::new()));
+
+    let registry_weak = Rc::downgrade(&registry);
+    let bound = Rc::clone(&metadata);
+    let registry_listener = registry
+        .add_listener_local()
+        .global(move |global| {
+            let is_default_metadata = global.type_ == pw::types::ObjectType::Metadata
+                && global
+                    .props
+                    .as_ref()
+                    .and_then(|props| props.get("metadata.name"))
+                    == Some("default");
+            if !is_default_metadata {
+                return;
+            }
+            let Some(registry) = registry_weak.upgrade() else {
+                return;
+            };
+            let proxy: pw::metadata::Metadata = match registry.bind(global) {
+                Ok(proxy) => proxy,
+                Err(e) => {
+                    tracing::warn!("Failed to bind default metadata: {}", e);
+                    return;
+                }
+            };
+
+            let defaults = Arc::clone(&defaults);
+            let listener = proxy
+                .add_listener_local()
+                .property(move |subject, key, _type, value| {
+                    defaults.on_property(subject, key, value);
+                    0
+                })
+                .register();
+            bound.borrow_mut().push((listener, proxy));
+        })
+        .register();
+
+    Ok(DefaultDeviceWatch {
+        _registry_listener: registry_listener,
+        _metadata: metadata,
+        _registry: registry,
+    })
+}
+
+/// Create a pw_stream for `config`, register its callbacks and connect it.
+fn connect_stream(
+    core: &pw::core::Core,
+    config: &StreamConfig,
+    buffer: Arc<RingBuffer>,
+    health: Arc<HealthMonitor>,
+    volume: Arc<AtomicU32>,
+    position: Arc<AtomicU64>,
+    recording: Arc<RecorderQueue>,
+) -> Result<ActiveStream> {
+    let category = match config.direction {
+        StreamDirection::Playback => "Playback",
+        StreamDirection::Recording => "Capture",
+    };
+
+    let stream = pw::stream::Stream::new(
+        core,
+        &config.name,
+        pw::properties::properties! {
+            *pw::keys::MEDIA_TYPE => "Audio",
+            *pw::keys::MEDIA_CATEGORY => category,
+            *pw::keys::MEDIA_ROLE => "Communication",
+            *pw::keys::NODE_NAME => config.name.as_str(),
+        },
+    )
+    .map_err(|e| BackendError::ConnectionFailed(format!("stream: {}", e)))?;
+
+    let capacity = buffer.capacity();
+    let state = ProcessState {
+        low_water: config.low_water_samples(capacity),
+        high_water: config.high_water_samples(capacity),
+        underrun_policy: config.underrun_policy,
+        buffer,
+        health: Arc::clone(&health),
+        volume,
+        position,
+        recording: Arc::clone(&recording),
+        format: config.format,
+        channels: config.channels as usize,
+        direction: config.direction,
+        scratch: vec![0.0; MAX_QUANTUM_FRAMES * config.channels as usize],
+    };
+
+    let listener = stream
+        .add_local_listener_with_user_data(state)
+        .state_changed(|_stream, state, _old, new| {
+            if let pw::stream::StreamState::Error(message) = new {
+                tracing::error!("PipeWire stream error: {}", message);
+                state.health.set_state(StreamState::Error);
+            }
+        })
+        .process(|stream, state| match state.direction {
+            StreamDirection::Playback => process_playback(stream, state),
+            StreamDirection::Recording => process_recording(stream, state),
+        })
+        .register()
+        .map_err(|e| BackendError::ConnectionFailed(format!("listener: {}", e)))?;
+
+    let format_pod = build_format_pod(config)?;
+    let mut params = [spa::pod::Pod::from_bytes(&format_pod)
+        .ok_or_else(|| BackendError::Internal("Invalid format pod".into()))?];
+
+    let direction = match config.direction {
+        StreamDirection::Playback => spa::utils::Direction::Output,
+        StreamDirection::Recording => spa::utils::Direction::Input,
+    };
+
+    stream
+        .connect(
+            direction,
+            None,
+            pw::stream::StreamFlags::AUTOCONNECT
+                | pw::stream::StreamFlags::MAP_BUFFERS
+                | pw::stream::StreamFlags::RT_PROCESS,
+            &mut params,
+        )
+        .map_err(|e| BackendError::ConnectionFailed(format!("connect: {}", e)))?;
+
+    Ok(ActiveStream {
+        _listener: listener,
+        stream,
+        recording,
+        health,
+    })
+}
+
+/// Serialize the EnumFormat param describing `config`'s raw audio format.
+fn build_format_pod(config: &StreamConfig) -> Result<Vec<u8>> {
+    let mut info = spa::param::audio::AudioInfoRaw::new();
+    info.set_format(PipeWireBackend::get_pw_format(config.format));
+    info.set_rate(config.sample_rate);
+    info.set_channels(config.channels);
+
+    let object = spa::pod::Object {
+        type_: spa::utils::SpaTypes::ObjectParamFormat.as_raw(),
+        id: spa::param::ParamType::EnumFormat.as_raw(),
+        properties: info.into(),
+    };
+
+    spa::pod::serialize::PodSerializer::serialize(
+        Cursor::new(Vec::new()),
+        &spa::pod::Value::Object(object),
+    )
+    .map(|(cursor, _)| cursor.into_inner())
+    .map_err(|e| BackendError::Internal(format!("Failed to build format pod: {:?}", e)))
+}
+
+/// Process callback for playback: ring buffer -> PipeWire buffer.
+fn process_playback(stream: &pw::stream::StreamRef, state: &mut ProcessState) {
+    let Some(mut pw_buffer) = stream.dequeue_buffer() else {
+        return;
+    };
+    let datas = pw_buffer.datas_mut();
+    let Some(data) = datas.first_mut() else {
+        return;
+    };
+
+    let stride = state.format.bytes_per_sample() * state.channels;
+    let size = data.data().map_or(0, |bytes| render_playback(state, bytes));
+
+    let chunk = data.chunk_mut();
+    *chunk.offset_mut() = 0;
+    *chunk.stride_mut() = stride as i32;
+    *chunk.size_mut() = size as u32;
+}
+
+/// Fill `bytes` with as many whole frames as fit, returning the byte count.
+///
+/// Outputs silence unless the stream is running or draining, so the
+/// prebuffer is not consumed before `start`. Samples are scaled by the
+/// stream volume; PipeWire's channel volumes are left at unity.
+fn render_playback(state: &mut ProcessState, bytes: &mut [u8]) -> usize {
+    let stride = state.format.bytes_per_sample() * state.channels;
+    let frames = (bytes.len() / stride).min(state.scratch.len() / state.channels);
+    let samples = frames * state.channels;
+    let scratch = &mut state.scratch[..samples];
+
+    let consuming = matches!(
+        state.health.get_state(),
+        StreamState::Running | StreamState::Draining
+    );
+    let read = if consuming {
+        state.buffer.read(scratch)
+    } else {
+        0
+    };
+    state
+        .position
+        .fetch_add((read / state.channels.max(1)) as u64, Ordering::Relaxed);
+    if read < samples {
+        scratch[read..].fill(0.0);
+        if consuming {
+            state.health.record_underrun();
+            state.health.apply_underrun(state.underrun_policy);
+        }
+    }
+    state.health.set_fill_level(state.buffer.fill_percent());
+    state.health.apply_water_marks(
+        state.buffer.available_read(),
+        state.low_water,
+        state.high_water,
+    );
+
+    let volume = f32::from_bits(state.volume.load(Ordering::Relaxed));
+    if volume != 1.0 {
+        for sample in &mut scratch[..read] {
+            *sample *= volume;
+        }
+    }
+
+    // Silent cycles are recorded too, keeping every stream on the device timeline
+    state.recording.push(scratch);
+    encode_samples(scratch, state.format, &mut bytes[..frames * stride]);
+    frames * stride
+}
+
+/// Process callback for recording: PipeWire buffer -> ring buffer.
+fn process_recording(stream: &pw::stream::StreamRef, state: &mut ProcessState) {
+    let Some(mut pw_buffer) = stream.dequeue_buffer() else {
+        return;
+    };
+    let datas = pw_buffer.datas_mut();
+    let Some(data) = datas.first_mut() else {
+        return;
+    };
+
+    let offset = data.chunk().offset() as usize;
+    let size = data.chunk().size() as usize;
+    let Some(bytes) = data.data() else {
+        return;
+    };
+    let end = (offset + size).min(bytes.len());
+    let bytes = &bytes[offset.min(end)..end];
+
+    let decoded = decode_samples(bytes, state.format, &mut state.scratch);
+    let written = state.buffer.write(&state.scratch[..decoded]);
+    if written < bytes.len() / state.format.bytes_per_sample() {
+        state.health.record_overrun();
+    }
+    state.health.set_fill_level(state.buffer.fill_percent());
+    state.health.apply_water_marks(
+        state.buffer.available_read(),
+        state.low_water,
+        state.high_water,
+    );
+}
+
+/// Convert f32 samples into interleaved little-endian `format` bytes.
+fn encode_samples(samples: &[f32], format: AudioFormat, out: &mut [u8]) {
+    let width = format.bytes_per_sample();
+    for (sample, dst) in samples.iter().zip(out.chunks_exact_mut(width)) {
+        let clamped = sample.clamp(-1.0, 1.0);
+        match format {
+            AudioFormat::F32LE => dst.copy_from_slice(&clamped.to_le_bytes()),
+            AudioFormat::S16LE => {
+                dst.copy_from_slice(&((clamped * i16::MAX as f32) as i16).to_le_bytes())
+            }
+            AudioFormat::S32LE => {
+                dst.copy_from_slice(&((clamped as f64 * i32::MAX as f64) as i32).to_le_bytes())
+            }
+        }
+    }
+}
+
+/// Convert interleaved little-endian `format` bytes into f32 samples,
+/// returning how many were written; stops when `out` is full.
+fn decode_samples(bytes: &[u8], format: AudioFormat, out: &mut [f32]) -> usize {
+    let width = format.bytes_per_sample();
+    let mut count = 0;
+    for (dst, src) in out.iter_mut().zip(bytes.chunks_exact(width)) {
+        *dst = match format {
+            AudioFormat::F32LE => f32::from_le_bytes([src[0], src[1], src[2], src[3]]),
+            AudioFormat::S16LE => i16::from_le_bytes([src[0], src[1]]) as f32 / i16::MAX as f32,
+            AudioFormat::S32LE => {
+                (i32::from_le_bytes([src[0], src[1], src[2], src[3]]) as f64
+                    / i32::MAX as f64) as f32
+            }
+        };
+        count += 1;
+    }
+    count
 }
 
 impl Backend for PipeWireBackend {
@@ -90,26 +703,68 @@ impl Backend for PipeWireBackend {
         true
     }
 
+    fn capabilities(&self) -> Capabilities {
+        Self::CAPABILITIES
+    }
+
     fn initialize(&mut self) -> Result<()> {
         if self.initialized {
             return Ok(());
         }
 
-        self.running.store(true, Ordering::SeqCst);
+        let (command_tx, command_rx) = pw::channel::channel::<PwCommand>();
+        let (ready_tx, ready_rx) = mpsc::channel();
+        let running = Arc::clone(&self.running);
+        let defaults = Arc::clone(&self.defaults);
+
+        let thread = thread::Builder::new()
+            .name("pipewire-main-loop".into())
+            .spawn(move || {
+                running.store(true, Ordering::SeqCst);
+                run_main_loop(command_rx, ready_tx, defaults);
+                running.store(false, Ordering::SeqCst);
+            })
+            .map_err(|e| BackendError::Internal(format!("Failed to spawn main loop: {}", e)))?;
+
+        match ready_rx.recv_timeout(COMMAND_TIMEOUT) {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => {
+                let _ = thread.join();
+                return Err(e);
+            }
+            Err(_) => {
+                // Stop the loop as soon as it starts, so the thread exits
+                let _ = command_tx.send(PwCommand::Terminate);
+                let _ = thread.join();
+                return Err(BackendError::ConnectionFailed(
+                    "Timed out waiting for PipeWire main loop".into(),
+                ));
+            }
+        }
+
+        self.commands = Some(Mutex::new(command_tx));
+        self.main_loop_thread = Some(thread);
         self.initialized = true;
 
         Ok(())
     }
 
     fn shutdown(&mut self) -> Result<()> {
-        self.running.store(false, Ordering::SeqCst);
-
         // Stop all streams
         let handles: Vec<_> = self.streams.keys().cloned().collect();
         for handle in handles {
             let _ = self.destroy_stream(handle);
         }
 
+        if self.commands.is_some() {
+            let _ = self.send_command(PwCommand::Terminate);
+            self.commands = None;
+        }
+        if let Some(thread) = self.main_loop_thread.take() {
+            let _ = thread.join();
+        }
+        self.running.store(false, Ordering::SeqCst);
+
         self.initialized = false;
         Ok(())
     }
@@ -129,25 +784,56 @@ impl Backend for PipeWireBackend {
             return Err(BackendError::InvalidConfig("Channels must be 1-8".into()));
         }
 
-        let handle = StreamHandle::new(self.next_handle);
-        self.next_handle += 1;
-
         // Create ring buffer sized for prebuffer + some headroom
         let buffer = Arc::new(RingBuffer::for_duration(
             config.sample_rate,
             config.channels,
-            config.buffer_size_ms + config.prebuffer_ms + 100, // Extra headroom
+            config.buffer_size_ms + config.effective_prebuffer_ms() + 100, // Extra headroom
         ));
+        config.validate_water_marks(buffer.capacity())?;
+        check_stream_limit(self.streams.len(), self.max_streams)?;
+
+        let handle = StreamHandle::new(self.next_handle);
+        self.next_handle += 1;
 
         let health = Arc::new(HealthMonitor::new());
         health.set_state(StreamState::Idle);
+        health.set_latency(config.latency_ms());
+        let volume = Arc::new(AtomicU32::new(1.0f32.to_bits()));
+        let position = Arc::new(AtomicU64::new(0));
+
+        // Connect the pw_stream on the main loop thread and wait for the result
+        let (reply_tx, reply_rx) = mpsc::channel();
+        self.send_command(PwCommand::CreateStream {
+            handle,
+            config: config.clone(),
+            buffer: Arc::clone(&buffer),
+            health: Arc::clone(&health),
+            volume: Arc::clone(&volume),
+            position: Arc::clone(&position),
+            recording: Arc::new(RecorderQueue::new(self.recorder.tap(handle, &config))),
+            reply: reply_tx,
+        })?;
+        let reply = reply_rx.recv_timeout(COMMAND_TIMEOUT).map_err(|_| {
+            // The loop may still connect the stream; queue its teardown
+            let _ = self.send_command(PwCommand::DestroyStream(handle));
+            BackendError::ConnectionFailed("Timed out creating stream".into())
+        })?;
+        reply?;
 
+        let gate = config.build_noise_gate().map(Mutex::new);
+        let filter = config.build_filter().map(Mutex::new);
+        let resampler = config.build_resampler().map(Mutex::new);
         let stream = PwStreamWrapper {
             config,
             buffer,
             health,
-            volume: 1.0,
-            state: StreamState::Idle,
+            volume,
+            position,
+            gate,
+            filter,
+            resampler,
+            tees: Vec::new(),
         };
 
         self.streams.insert(handle, stream);
@@ -159,68 +845,120 @@ impl Backend for PipeWireBackend {
         self.streams
             .remove(&handle)
             .ok_or(BackendError::StreamNotFound(handle))?;
+        for stream in self.streams.values_mut() {
+            stream.tees.retain(|&tee| tee != handle);
+        }
+        // The main loop may already be gone during shutdown, and tees were
+        // never connected; both are ignored there
+        let _ = self.send_command(PwCommand::DestroyStream(handle));
         Ok(())
     }
 
+    fn tee(&mut self, source: StreamHandle) -> Result<StreamHandle> {
+        let stream = self.get_stream(source)?;
+        let config = stream.config.tee_config()?;
+        let capacity = stream.buffer.capacity();
+        check_stream_limit(self.streams.len(), self.max_streams)?;
+
+        let handle = StreamHandle::new(self.next_handle);
+        self.next_handle += 1;
+
+        // Filled only by writes to the source, never by a pw_stream
+        let health = Arc::new(HealthMonitor::new());
+        health.set_state(StreamState::Running);
+        health.set_latency(config.latency_ms());
+        let tee = PwStreamWrapper {
+            config,
+            buffer: Arc::new(RingBuffer::new(capacity)),
+            health,
+            volume: Arc::new(AtomicU32::new(1.0f32.to_bits())),
+            position: Arc::new(AtomicU64::new(0)),
+            gate: None,
+            filter: None,
+            resampler: None,
+            tees: Vec::new(),
+        };
+
+        self.streams.insert(handle, tee);
+        self.get_stream_mut(source)?.tees.push(handle);
+        Ok(handle)
+    }
+
+    fn stream_handles(&self) -> Vec<StreamHandle> {
+        self.streams.keys().copied().collect()
+    }
+
+    fn set_max_streams(&mut self, max: usize) {
+        self.max_streams = max;
+    }
+
     fn get_state(&self, handle: StreamHandle) -> Result<StreamState> {
-        Ok(self.get_stream(handle)?.state)
+        Ok(self.get_stream(handle)?.state())
     }
 
     fn start(&mut self, handle: StreamHandle) -> Result<()> {
         let stream = self.get_stream_mut(handle)?;
-        match stream.state {
+        match stream.state() {
             StreamState::Idle | StreamState::Paused => {
+                stream.position.store(0, Ordering::Relaxed);
                 // Check prebuffer requirement
-                let prebuffer_samples = stream.config.prebuffer_samples();
-                if stream.buffer.available_read() >= prebuffer_samples {
-                    stream.state = StreamState::Running;
+                let high_water = stream.config.high_water_samples(stream.buffer.capacity());
+                if stream.buffer.available_read() >= high_water {
                     stream.health.set_state(StreamState::Running);
                 } else {
-                    stream.state = StreamState::Prebuffering;
                     stream.health.set_state(StreamState::Prebuffering);
                 }
                 Ok(())
             }
-            _ => Err(BackendError::InvalidState {
+            actual => Err(BackendError::InvalidState {
                 expected: StreamState::Idle,
-                actual: stream.state,
+                actual,
             }),
         }
     }
 
     fn stop(&mut self, handle: StreamHandle) -> Result<()> {
         let stream = self.get_stream_mut(handle)?;
-        stream.state = StreamState::Stopped;
         stream.health.set_state(StreamState::Stopped);
         stream.buffer.clear();
+        stream.buffer.reset_high_water();
+        stream.position.store(0, Ordering::Relaxed);
+        // A restarted stream should not open on a gate closed by old audio
+        if let Some(gate) = &stream.gate {
+            gate.lock().reset();
+        }
+        // Nor ring out the tail of what was cleared from the buffer
+        if let Some(filter) = &stream.filter {
+            filter.lock().reset();
+        }
         Ok(())
     }
 
     fn pause(&mut self, handle: StreamHandle) -> Result<()> {
         let stream = self.get_stream_mut(handle)?;
-        if stream.state == StreamState::Running {
-            stream.state = StreamState::Paused;
-            stream.health.set_state(StreamState::Paused);
-            Ok(())
-        } else {
-            Err(BackendError::InvalidState {
+        match stream.state() {
+            StreamState::Running => {
+                stream.health.set_state(StreamState::Paused);
+                Ok(())
+            }
+            actual => Err(BackendError::InvalidState {
                 expected: StreamState::Running,
-                actual: stream.state,
-            })
+                actual,
+            }),
         }
     }
 
     fn resume(&mut self, handle: StreamHandle) -> Result<()> {
         let stream = self.get_stream_mut(handle)?;
-        if stream.state == StreamState::Paused {
-            stream.state = StreamState::Running;
-            stream.health.set_state(StreamState::Running);
-            Ok(())
-        } else {
-            Err(BackendError::InvalidState {
+        match stream.state() {
+            StreamState::Paused => {
+                stream.health.set_state(StreamState::Running);
+                Ok(())
+            }
+            actual => Err(BackendError::InvalidState {
                 expected: StreamState::Paused,
-                actual: stream.state,
-            })
+                actual,
+            }),
         }
     }
 
@@ -233,24 +971,50 @@ impl Backend for PipeWireBackend {
             ));
         }
 
-        let written = stream.buffer.write(samples);
+        let resampled;
+        let (samples, consumed) = match &stream.resampler {
+            Some(resampler) => {
+                let mut resampler = resampler.lock();
+                // All or nothing, so the resampler state never runs ahead
+                // of the buffered audio
+                if resampler.output_len(samples.len()) > stream.buffer.available_write() {
+                    stream.health.record_overrun();
+                    return Ok(0);
+                }
+                resampled = resampler.process(samples);
+                (&resampled[..], Some(samples.len()))
+            }
+            None => (samples, None),
+        };
+
+        let filtered;
+        let buffered = match &stream.filter {
+            Some(filter) => {
+                // Only filter what fits so the filter state tracks the buffered audio
+                let fits = samples.len().min(stream.buffer.available_write());
+                let mut block = samples[..fits].to_vec();
+                filter.lock().process(&mut block);
+                filtered = block;
+                &filtered[..]
+            }
+            None => samples,
+        };
+        let written = stream.buffer.write(buffered);
+        self.mirror(&stream.tees, &buffered[..written]);
 
         // Update health metrics
         stream.health.set_fill_level(stream.buffer.fill_percent());
+        stream.health.deliver_backpressure();
 
         if written < samples.len() {
             stream.health.record_overrun();
         }
 
-        // Check if we've reached prebuffer threshold
-        if stream.health.get_state() == StreamState::Prebuffering {
-            let prebuffer_samples = stream.config.prebuffer_samples();
-            if stream.buffer.available_read() >= prebuffer_samples {
-                stream.health.set_state(StreamState::Running);
-            }
-        }
+        // Leave prebuffering once the high water mark is reached
+        stream.update_water_marks();
 
-        Ok(written)
+        // Report input samples for resampled writes
+        Ok(consumed.unwrap_or(written))
     }
 
     fn read(&self, handle: StreamHandle, buffer: &mut [f32]) -> Result<usize> {
@@ -263,47 +1027,101 @@ impl Backend for PipeWireBackend {
         }
 
         let read = stream.buffer.read(buffer);
+        if let Some(gate) = &stream.gate {
+            gate.lock().process(&mut buffer[..read]);
+        }
+        stream
+            .position
+            .fetch_add((read / stream.config.channels.max(1) as usize) as u64, Ordering::Relaxed);
 
         // Update health metrics
         stream.health.set_fill_level(stream.buffer.fill_percent());
+        stream.health.deliver_backpressure();
 
         if read < buffer.len() {
             stream.health.record_underrun();
         }
+        stream.update_water_marks();
 
         Ok(read)
     }
 
     fn set_volume(&mut self, handle: StreamHandle, volume: f32) -> Result<()> {
         let stream = self.get_stream_mut(handle)?;
-        stream.volume = volume.clamp(0.0, 1.0);
-        // In full implementation, would update PipeWire stream volume property
+        // Picked up by the process callback on its next cycle
+        stream
+            .volume
+            .store(volume.clamp(0.0, 1.0).to_bits(), Ordering::Relaxed);
         Ok(())
     }
 
     fn get_volume(&self, handle: StreamHandle) -> Result<f32> {
-        Ok(self.get_stream(handle)?.volume)
+        let stream = self.get_stream(handle)?;
+        Ok(f32::from_bits(stream.volume.load(Ordering::Relaxed)))
+    }
+
+    fn get_position(&self, handle: StreamHandle) -> Result<u64> {
+        Ok(self.get_stream(handle)?.position.load(Ordering::Relaxed))
+    }
+
+    fn recorder(&self) -> &RecorderSlot {
+        &self.recorder
+    }
+
+    fn stop_recording(&self) -> Option<Arc<Recorder>> {
+        // Collect what the process callbacks queued since the last flush
+        let (reply_tx, reply_rx) = mpsc::channel();
+        if self.send_command(PwCommand::FlushRecording(reply_tx)).is_ok() {
+            let _ = reply_rx.recv_timeout(COMMAND_TIMEOUT);
+        }
+        self.recorder.detach()
     }
 
     fn get_health(&self, handle: StreamHandle) -> Result<HealthMetrics> {
-        Ok(self.get_stream(handle)?.health.snapshot())
+        let stream = self.get_stream(handle)?;
+        Ok(HealthMetrics {
+            peak_fill_level: stream.buffer.peak_fill_percent(),
+            ..stream.health.snapshot()
+        })
+    }
+
+    fn set_backpressure_listener(
+        &self,
+        handle: StreamHandle,
+        high: f32,
+        low: f32,
+        listener: Option<BackpressureListener>,
+    ) -> Result<()> {
+        self.get_stream(handle)?.health.set_backpressure(high, low, listener)
+    }
+
+    fn clock(&self) -> Arc<dyn Clock> {
+        Arc::clone(&self.clock)
     }
 
-    fn drain(&self, handle: StreamHandle) -> Result<()> {
+    fn drain_step(&self, handle: StreamHandle) -> Result<bool> {
         let stream = self.get_stream(handle)?;
 
-        // Wait for buffer to empty (with timeout)
-        let start = std::time::Instant::now();
-        let timeout = std::time::Duration::from_secs(5);
+        // Switch to draining first so the process callback plays out the
+        // tail even below the low water mark
+        if matches!(stream.state(), StreamState::Running | StreamState::Prebuffering) {
+            stream.health.set_state(StreamState::Draining);
+        }
 
-        while stream.buffer.available_read() > 0 {
-            if start.elapsed() > timeout {
-                return Err(BackendError::Internal("Drain timeout".into()));
-            }
-            std::thread::sleep(std::time::Duration::from_millis(10));
+        if !stream.buffer.is_empty() {
+            return Ok(false);
+        }
+        if stream.state() == StreamState::Draining {
+            stream.health.set_state(StreamState::Idle);
         }
+        Ok(true)
+    }
 
-        stream.health.set_state(StreamState::Draining);
+    fn end_drain(&self, handle: StreamHandle) -> Result<()> {
+        let stream = self.get_stream(handle)?;
+        if stream.state() == StreamState::Draining {
+            stream.health.set_state(StreamState::Running);
+        }
         Ok(())
     }
 
@@ -344,6 +1162,10 @@ impl Backend for PipeWireBackend {
             .next()
             .ok_or_else(|| BackendError::NotAvailable("No recording device".into()))
     }
+
+    fn set_default_device_listener(&mut self, listener: Option<DefaultDeviceListener>) {
+        *self.defaults.listener.lock() = listener;
+    }
 }
 
 impl Drop for PipeWireBackend {
@@ -351,3 +1173,104 @@ impl Drop for PipeWireBackend {
         let _ = self.shutdown();
     }
 }
+
+#[cfg(all(test, feature = "pipewire-integration"))]
+mod tests {
+    use super::*;
+    use std::time::Instant;
+
+    #[test]
+    fn test_playback_stream_consumes_samples() {
+        let mut backend = PipeWireBackend::new().unwrap();
+        backend.initialize().unwrap();
+
+        let config = StreamConfig::default();
+        let prebuffer = config.prebuffer_samples();
+        let handle = backend.create_stream(config).unwrap();
+
+        let samples = vec![0.0f32; prebuffer];
+        assert_eq!(backend.write(handle, &samples).unwrap(), prebuffer);
+
+        backend.start(handle).unwrap();
+        assert_eq!(backend.get_state(handle).unwrap(), StreamState::Running);
+
+        // The process callback should start pulling from the ring buffer
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while backend.get_stream(handle).unwrap().buffer.available_read() == prebuffer {
+            assert!(Instant::now() < deadline, "PipeWire never consumed samples");
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        backend.shutdown().unwrap();
+    }
+
+    #[test]
+    fn test_default_sink_change_notifies_listener() {
+        // Subscribe before initializing, as a JS caller may
+        let mut manager = crate::AudioManager::new();
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let seen = Arc::clone(&changes);
+        manager.subscribe_default_device(Arc::new(move |direction, name: &str| {
+            seen.lock().push((direction, name.to_string()));
+        }));
+
+        let backend = PipeWireBackend::new().unwrap();
+        let defaults = Arc::clone(&backend.defaults);
+        manager.install_backend(Box::new(backend));
+
+        // Simulate the metadata events the session manager emits
+        let sink = Some("default.audio.sink");
+        defaults.on_property(PW_ID_CORE, sink, Some(r#"{ "name": "alsa_output.builtin" }"#));
+        defaults.on_property(PW_ID_CORE, sink, Some(r#"{ "name": "alsa_output.builtin" }"#));
+        defaults.on_property(42, sink, Some(r#"{ "name": "bluez_output.headset" }"#));
+        defaults.on_property(PW_ID_CORE, sink, Some(r#"{ "name": "bluez_output.headset" }"#));
+
+        // Only the switch away from the starting default is reported
+        assert_eq!(
+            *changes.lock(),
+            vec![(StreamDirection::Playback, "bluez_output.headset".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_volume_scales_process_output() {
+        let mut backend = PipeWireBackend::new().unwrap();
+        backend.initialize().unwrap();
+
+        let handle = backend.create_stream(StreamConfig::default()).unwrap();
+        backend.set_volume(handle, 0.5).unwrap();
+        assert!((backend.get_volume(handle).unwrap() - 0.5).abs() < 0.001);
+
+        // Drive the render path directly with a known input
+        let wrapper = backend.get_stream(handle).unwrap();
+        let mut state = ProcessState {
+            buffer: Arc::new(RingBuffer::new(64)),
+            health: Arc::new(HealthMonitor::new()),
+            volume: Arc::clone(&wrapper.volume),
+            position: Arc::clone(&wrapper.position),
+            recording: Arc::new(RecorderQueue::new(
+                RecorderSlot::new().tap(handle, &StreamConfig::default()),
+            )),
+            format: AudioFormat::F32LE,
+            channels: 1,
+            direction: StreamDirection::Playback,
+            low_water: 0,
+            high_water: 0,
+            underrun_policy: UnderrunPolicy::Silence,
+            scratch: vec![0.0; 64],
+        };
+        state.health.set_state(StreamState::Running);
+        state.buffer.write(&[0.8, -0.4, 0.2, 1.0]);
+
+        let mut bytes = vec![0u8; 4 * 4];
+        assert_eq!(render_playback(&mut state, &mut bytes), bytes.len());
+
+        let mut output = vec![0.0; 4];
+        assert_eq!(decode_samples(&bytes, AudioFormat::F32LE, &mut output), 4);
+        for (out, expected) in output.iter().zip([0.4, -0.2, 0.1, 0.5]) {
+            assert!((out - expected).abs() < 0.001);
+        }
+
+        backend.shutdown().unwrap();
+    }
+}